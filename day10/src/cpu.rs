@@ -0,0 +1,232 @@
+use std::fmt;
+
+// Registers are named by a single letter, and instructions that work on one
+// end in its name, like addx or mulw.
+pub const REGISTER_NAMES : [char; 4] = ['w', 'x', 'y', 'z'];
+pub type Reg = usize;
+pub const X : Reg = 1;
+
+fn parse_register(name : &str) -> Option<Reg> {
+  let mut chars = name.chars();
+  match (chars.next(), chars.next()) {
+    (Some(ch), None) => REGISTER_NAMES.iter().position(|reg| *reg == ch),
+    _ => None,
+  }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub enum Operand {
+  Imm(i64),
+  Reg(Reg),
+}
+
+impl Operand {
+  fn parse(token : &str) -> Result<Operand, String> {
+    if let Some(reg) = parse_register(token) {
+      return Ok(Operand::Reg(reg));
+    }
+    token.parse::<i64>()
+      .map(Operand::Imm)
+      .map_err(|_| format!("{} is neither a number nor a register", token))
+  }
+}
+
+impl fmt::Display for Operand {
+  fn fmt(&self, f : &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      Operand::Imm(val) => write!(f, "{}", val),
+      Operand::Reg(reg) => write!(f, "{}", REGISTER_NAMES[*reg]),
+    }
+  }
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Op {
+  Noop,
+  // reg += operand
+  Add,
+  // reg -= operand
+  Sub,
+  // reg *= operand
+  Mul,
+  // Jump by an offset relative to this instruction.
+  Jmp,
+  // Jump by the second operand if the first isn't zero.
+  Jnz,
+  // Stop the program.
+  Halt,
+}
+
+pub struct Opcode {
+  pub name : &'static str,
+  pub op : Op,
+  pub cycles : usize,
+  // Whether the mnemonic ends in the name of the register it writes.
+  pub has_register : bool,
+  pub operand_count : usize,
+}
+
+// Every instruction the CPU knows about, and how many cycles it takes.
+pub const OPCODES : [Opcode; 7] = [
+  Opcode { name : "noop", op : Op::Noop, cycles : 1, has_register : false, operand_count : 0 },
+  Opcode { name : "add", op : Op::Add, cycles : 2, has_register : true, operand_count : 1 },
+  Opcode { name : "sub", op : Op::Sub, cycles : 2, has_register : true, operand_count : 1 },
+  Opcode { name : "mul", op : Op::Mul, cycles : 4, has_register : true, operand_count : 1 },
+  Opcode { name : "jmp", op : Op::Jmp, cycles : 1, has_register : false, operand_count : 1 },
+  Opcode { name : "jnz", op : Op::Jnz, cycles : 2, has_register : false, operand_count : 2 },
+  Opcode { name : "halt", op : Op::Halt, cycles : 1, has_register : false, operand_count : 0 },
+];
+
+pub struct Instr {
+  pub opcode : &'static Opcode,
+  // Only meaningful if the opcode has a register.
+  pub reg : Reg,
+  pub operands : Vec<Operand>,
+}
+
+impl Instr {
+  pub fn parse(line : &str) -> Result<Instr, String> {
+    let parts : Vec<&str> = line.split_whitespace().collect();
+    let (mnemonic, operands) = parts.split_first().ok_or("empty instruction")?;
+
+    for opcode in &OPCODES {
+      let Some(suffix) = mnemonic.strip_prefix(opcode.name) else {
+        continue;
+      };
+      let reg = match (opcode.has_register, suffix) {
+        (false, "") => 0,
+        (true, _) => match parse_register(suffix) {
+          Some(reg) => reg,
+          None => continue,
+        },
+        _ => continue,
+      };
+
+      if operands.len() != opcode.operand_count {
+        return Err(format!("{} takes {} operands, got {}", mnemonic, opcode.operand_count, operands.len()));
+      }
+      let operands = operands.iter().map(|token| Operand::parse(token)).collect::<Result<_, _>>()?;
+      return Ok(Instr { opcode, reg, operands });
+    }
+
+    Err(format!("illegal instruction {}", mnemonic))
+  }
+}
+
+impl fmt::Display for Instr {
+  fn fmt(&self, f : &mut fmt::Formatter) -> fmt::Result {
+    write!(f, "{}", self.opcode.name)?;
+    if self.opcode.has_register {
+      write!(f, "{}", REGISTER_NAMES[self.reg])?;
+    }
+    for operand in &self.operands {
+      write!(f, " {}", operand)?;
+    }
+    fmt::Result::Ok(())
+  }
+}
+
+// Gets told about every cycle the CPU runs. During a cycle the registers
+// still hold what they held when the cycle started; after it, whatever
+// instruction finished in that cycle has taken effect. An error stops the
+// CPU.
+pub trait Observer {
+  fn during_cycle(&mut self, _cpu : &Cpu) -> Result<(), String> {
+    Ok(())
+  }
+
+  fn after_cycle(&mut self, _cpu : &Cpu) -> Result<(), String> {
+    Ok(())
+  }
+}
+
+pub struct Cpu {
+  pub registers : [i64; REGISTER_NAMES.len()],
+  // Index of the instruction being executed.
+  pub pc : usize,
+  // Number of the current cycle, starting from 1.
+  pub cycle : usize,
+}
+
+impl Default for Cpu {
+  // Every register starts at zero, except x which starts at 1.
+  fn default() -> Cpu {
+    let mut registers = [0; REGISTER_NAMES.len()];
+    registers[X] = 1;
+    Cpu { registers, pc : 0, cycle : 0 }
+  }
+}
+
+impl Cpu {
+  fn value(&self, operand : &Operand) -> i64 {
+    match operand {
+      Operand::Imm(val) => *val,
+      Operand::Reg(reg) => self.registers[*reg],
+    }
+  }
+
+  // Applies an operation to the instruction's register and its operand.
+  fn update_register(&mut self, instr : &Instr, op : fn(i64, i64) -> Option<i64>) -> Result<(), String> {
+    let reg = instr.reg;
+    self.registers[reg] = op(self.registers[reg], self.value(&instr.operands[0]))
+      .ok_or_else(|| format!("{} at {} overflows register {}", instr, self.pc, REGISTER_NAMES[reg]))?;
+    Ok(())
+  }
+
+  // Carries out an instruction once its last cycle is over. Returns false
+  // if the program should stop.
+  fn execute(&mut self, instr : &Instr) -> Result<bool, String> {
+    let mut offset = 1;
+    match instr.opcode.op {
+      Op::Noop => (),
+      Op::Add => self.update_register(instr, i64::checked_add)?,
+      Op::Sub => self.update_register(instr, i64::checked_sub)?,
+      Op::Mul => self.update_register(instr, i64::checked_mul)?,
+      Op::Jmp => offset = self.value(&instr.operands[0]),
+      Op::Jnz => {
+        if self.value(&instr.operands[0]) != 0 {
+          offset = self.value(&instr.operands[1]);
+        }
+      },
+      Op::Halt => return Ok(false),
+    }
+
+    match (self.pc as i64).checked_add(offset) {
+      Some(next_pc) if next_pc >= 0 => self.pc = next_pc as usize,
+      Some(_) => return Err(format!("{} at {} jumps before the start of the program", instr, self.pc)),
+      // Far past the end, which stops the program just the same.
+      None => self.pc = usize::MAX,
+    }
+    Ok(true)
+  }
+
+  // Runs until a halt or until the program counter leaves the end of the
+  // program. Gives up after max_cycles, in case the program loops forever.
+  pub fn run(&mut self, program : &[Instr], observers : &mut [&mut dyn Observer], max_cycles : usize)
+      -> Result<(), String> {
+    while let Some(instr) = program.get(self.pc) {
+      let mut running = true;
+      for cycle in 1..=instr.opcode.cycles {
+        self.cycle += 1;
+        if self.cycle > max_cycles {
+          return Err(format!("still running after {} cycles", max_cycles));
+        }
+
+        for observer in observers.iter_mut() {
+          observer.during_cycle(self)?;
+        }
+        if cycle == instr.opcode.cycles {
+          running = self.execute(instr)?;
+        }
+        for observer in observers.iter_mut() {
+          observer.after_cycle(self)?;
+        }
+      }
+
+      if !running {
+        break;
+      }
+    }
+    Ok(())
+  }
+}
@@ -1,106 +1,113 @@
+use std::env;
+use std::error::Error;
 use std::io;
 
-#[derive(Debug)]
-enum Instr {
-  Noop,
-  AddX(i64),
-}
+mod cpu;
 
-impl Instr {
-  fn cycle_count(&self) -> usize {
-    match *self {
-      Self::Noop => 1,
-      Self::AddX(_) => 2,
-    }
-  }
-}
+use cpu::{Cpu, Instr, Observer, REGISTER_NAMES, X};
 
-fn parse_input(lines : impl Iterator<Item = io::Result<String>>) -> Vec<Instr> {
+fn parse_input(lines : impl Iterator<Item = io::Result<String>>) -> Result<Vec<Instr>, Box<dyn Error>> {
   let mut instrs = Vec::new();
 
-  for line in lines {
-    let line = line.unwrap();
-    let parts : Vec<&str> = line.split_whitespace().collect();
-    assert!(parts.len() > 0);
-    assert!(parts.len() <= 2);
-    instrs.push(
-        match parts[0] {
-          "noop" => Instr::Noop,
-          "addx" => Instr::AddX(parts[1].parse::<i64>().unwrap()),
-          _ => panic!("illegal instruction {}", parts[1]),
-        }
-      );
+  for (idx, line) in lines.enumerate() {
+    let line = line?;
+    if line.trim().is_empty() {
+      continue;
+    }
+    instrs.push(Instr::parse(&line).map_err(|msg| format!("line {}: {}", idx + 1, msg))?);
   }
 
-  instrs
+  Ok(instrs)
 }
 
-fn execute(instrs : Vec<Instr>) -> (i64, String) {
-  const DISPLAY_COLS : usize = 40usize;
-  const DISPLAY_ROWS : usize = 7usize;
+// Sums x times the cycle number during cycle 20 and every 40 cycles after.
+#[derive(Default)]
+struct SignalStrength {
+  total : i64,
+}
 
-  let mut total_signal_strength = 0i64;
-  let mut display = String::with_capacity((DISPLAY_COLS + 1) * DISPLAY_ROWS);
+impl Observer for SignalStrength {
+  fn during_cycle(&mut self, cpu : &Cpu) -> Result<(), String> {
+    let cycle = cpu.cycle;
+    if cycle >= 20 && (cycle - 20).is_multiple_of(40) {
+      let signal_strength = i64::try_from(cycle).ok()
+        .and_then(|cycle| cpu.registers[X].checked_mul(cycle))
+        .ok_or_else(|| format!("signal strength overflows in cycle {} with x={}", cycle, cpu.registers[X]))?;
+      self.total = self.total.checked_add(signal_strength)
+        .ok_or_else(|| format!("sum of signal strengths overflows in cycle {}", cycle))?;
 
-  let mut pc = 0;
-  let mut reg_x = 1i64;
-  let mut instr = &Instr::Noop;
-  let mut cycle_counter = 0usize;
-  let mut x_pos = 0usize;
+      #[cfg(debug_assertions)]
+      println!("signal strength: {} {} {}", cycle, cpu.registers[X], signal_strength);
+    }
+    Ok(())
+  }
+}
 
-  for cycle in 1..usize::MAX {
+// Draws one pixel per cycle, lit if the three pixel wide sprite centred on
+// x covers it.
+#[derive(Default)]
+struct Crt {
+  display : String,
+  x_pos : usize,
+}
 
-    // Update signal strength at start of cycle.
-    if (cycle == 20) || (cycle > 20 && (cycle - 20) % 40 == 0) {
-      // Update signal value.
-      let signal_strength = reg_x * cycle as i64;
-      total_signal_strength += signal_strength;
+impl Crt {
+  const DISPLAY_COLS : usize = 40usize;
+}
 
-      #[cfg(debug_assertions)]
-      println!("signal strength: {} {} {}", cycle, reg_x, signal_strength);
+impl Observer for Crt {
+  fn during_cycle(&mut self, cpu : &Cpu) -> Result<(), String> {
+    // abs_diff can't overflow, however far off screen x is.
+    let pixel = if (self.x_pos as i64).abs_diff(cpu.registers[X]) <= 1 { '#' } else { '.' };
+    self.display.push(pixel);
+    self.x_pos = (self.x_pos + 1) % Crt::DISPLAY_COLS;
+    if self.x_pos == 0 {
+      self.display.push('\n');
     }
+    Ok(())
+  }
+}
 
-    let pixel = if (x_pos as i64 >= reg_x - 1) && (x_pos as i64 <= reg_x + 1) { '#' } else { '.' };
-    display.push(pixel);
-    x_pos = (x_pos + 1) % DISPLAY_COLS;
-    if x_pos == 0 {
-      display.push('\n');
-    }
+// Prints the registers at the end of every cycle.
+struct Trace;
 
-    if cycle_counter == 0 {
-      // Fetch instruction.
-      instr = &instrs[pc];
-      cycle_counter = instr.cycle_count();
-    }
+impl Observer for Trace {
+  fn after_cycle(&mut self, cpu : &Cpu) -> Result<(), String> {
+    let registers : Vec<String> = REGISTER_NAMES.iter().zip(cpu.registers)
+      .map(|(name, val)| format!("{}={}", name, val))
+      .collect();
+    println!("after cycle {}: pc={} {}", cpu.cycle, cpu.pc, registers.join(" "));
+    Ok(())
+  }
+}
 
-    cycle_counter -= 1;
-    if cycle_counter > 0 {
-      // Stall.
-      continue;
+const MAX_CYCLES : usize = 1000000;
+
+fn main() -> Result<(), Box<dyn Error>> {
+  let mut trace = false;
+  let mut max_cycles = MAX_CYCLES;
+  let mut args = env::args().skip(1);
+  while let Some(arg) = args.next() {
+    match arg.as_str() {
+      "--trace" => trace = true,
+      "--max-cycles" => max_cycles = args.next().ok_or("--max-cycles requires a value")?.parse::<usize>()?,
+      _ => return Err(format!("unexpected argument: {}", arg).into()),
     }
+  }
 
-    // Execute current instruction when cycle counter reaches 0.
-    match instr {
-      Instr::Noop => {},
-      Instr::AddX(val) => {
-        reg_x += val;
-      },
-    }
+  let instrs = parse_input(io::stdin().lines())?;
 
-    // Move to next instruction.
-    pc = pc + 1;
-    if !(pc < instrs.len()) {
-      // Reached end of instruction sequence.
-      break;
-    }
+  let mut signal_strength = SignalStrength::default();
+  let mut crt = Crt::default();
+  let mut tracer = Trace;
+  let mut observers : Vec<&mut dyn Observer> = vec![&mut signal_strength, &mut crt];
+  if trace {
+    observers.push(&mut tracer);
   }
+  Cpu::default().run(&instrs, &mut observers, max_cycles)?;
 
-  (total_signal_strength, display)
-}
+  println!("part 1: {}", signal_strength.total);
+  println!("part 2:\n{}", crt.display);
 
-fn main() {
-  let instrs = parse_input(io::stdin().lines());
-  let (total_signal_strength, display) = execute(instrs);
-  println!("part 1: {}", total_signal_strength);
-  println!("part 2:\n{}", display);
+  Ok(())
 }
@@ -1,77 +1,238 @@
+use std::env;
+use std::error::Error;
+use std::fmt;
 use std::io;
 
-fn item_priority(item : char) -> u8 {
-  let prio : u8;
-  if (item as u8 >= 'a' as u8) && (item as u8 <= 'z' as u8) {
-    prio = item as u8 - 'a' as u8 + 1;
-  } else if (item as u8 >= 'A' as u8) && (item as u8 <= 'Z' as u8) {
-    prio = item as u8 - 'A' as u8 + 27;
-  } else {
-    panic!("unexpected item: {}", item);
+const DEFAULT_GROUP_SIZE : usize = 3;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum RucksackError {
+  OddLength { line : usize, len : usize },
+  InvalidItem { line : usize, item : char },
+  IncompleteGroup { group_size : usize, leftover : usize },
+}
+
+impl fmt::Display for RucksackError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      RucksackError::OddLength { line, len } =>
+        write!(f, "line {}: odd number of items ({}) can't be split into two compartments", line, len),
+      RucksackError::InvalidItem { line, item } =>
+        write!(f, "line {}: unexpected item {:?}, expected a-z or A-Z", line, item),
+      RucksackError::IncompleteGroup { group_size, leftover } =>
+        write!(f, "{} rucksack(s) left over, expected groups of {}", leftover, group_size),
+    }
+  }
+}
+
+impl Error for RucksackError {}
+
+// Priority of an item: a-z are 1-26, A-Z are 27-52.
+fn item_priority(item : char) -> Option<u8> {
+  match item {
+    'a'..='z' => Some(item as u8 - b'a' + 1),
+    'A'..='Z' => Some(item as u8 - b'A' + 27),
+    _ => None,
   }
-  return prio;
 }
 
-fn count_items(items : &[char], item_counts : &mut [u32; 256]) {
-  for item in items {
-    item_counts[*item as usize] += 1;
+// Inverse of item_priority.
+fn priority_item(prio : u8) -> char {
+  match prio {
+    1..=26 => (b'a' + prio - 1) as char,
+    27..=52 => (b'A' + prio - 27) as char,
+    _ => panic!("invalid priority: {}", prio),
   }
 }
 
-fn sum_priorities(lines : impl Iterator<Item = io::Result<String>>) -> (u64, u64) {
-  let mut priority : u64 = 0;
-  let mut badge_priority : u64 = 0;
-  let mut group_index : usize = 0;
-  let mut group_counts : [[u32; 256]; 3] = [[0; 256]; 3];
+// Set of items, one bit per priority.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct ItemSet(u64);
 
-  for line in lines {
-    let chars : Vec<char> = line.unwrap().chars().collect();
-    let len = chars.len();
+impl ItemSet {
+  const EMPTY : ItemSet = ItemSet(0);
+  // Bits 1-52, one for each valid priority.
+  const ALL : ItemSet = ItemSet(((1u64 << 52) - 1) << 1);
+
+  fn parse(items : &str, line : usize) -> Result<ItemSet, RucksackError> {
+    let mut set = ItemSet::EMPTY;
+    for item in items.chars() {
+      let prio = item_priority(item).ok_or(RucksackError::InvalidItem { line, item })?;
+      set.0 |= 1u64 << prio;
+    }
+    Ok(set)
+  }
+
+  fn intersect(self, other : ItemSet) -> ItemSet {
+    ItemSet(self.0 & other.0)
+  }
+
+  fn union(self, other : ItemSet) -> ItemSet {
+    ItemSet(self.0 | other.0)
+  }
 
-    // Line length must be even to split into two.
-    assert_eq!(len % 2, 0);
+  fn len(self) -> u32 {
+    self.0.count_ones()
+  }
 
-    let mut item_counts_1 : [u32; 256] = [0; 256];
-    let mut item_counts_2 : [u32; 256] = [0; 256];
+  fn priorities(self) -> impl Iterator<Item = u8> {
+    (1u8..=52).filter(move |prio| self.0 & (1u64 << prio) != 0)
+  }
 
-    count_items(&chars[..len/2], &mut item_counts_1);
-    count_items(&chars[len/2..], &mut item_counts_2);
+  fn priority_sum(self) -> u64 {
+    self.priorities().map(|prio| prio as u64).sum()
+  }
+}
 
-    for i in 0u8..255u8 {
-      let item_1_count = item_counts_1[i as usize];
-      let item_2_count = item_counts_2[i as usize];
-      if item_1_count > 0 && item_2_count > 0 {
-        // Expect only alphabetic characters.
-        assert!((i >= 'a' as u8 && i <= 'z' as u8) ||
-                (i >= 'A' as u8 && i <= 'Z' as u8));
-        priority += item_priority(i as char) as u64;
+// Lists items as `p (16)`, or `none` for an empty set.
+impl fmt::Display for ItemSet {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    if self.len() == 0 {
+      return write!(f, "none");
+    }
+    for (idx, prio) in self.priorities().enumerate() {
+      if idx > 0 {
+        write!(f, ", ")?;
       }
+      write!(f, "{} ({})", priority_item(prio), prio)?;
     }
+    fmt::Result::Ok(())
+  }
+}
 
-    count_items(&chars, &mut group_counts[group_index]);
+#[derive(Debug, Clone, Copy)]
+struct Rucksack {
+  compartments : [ItemSet; 2],
+}
 
-    group_index += 1;
-    if group_index == 3 {
-      for i in 0u8..255u8 {
-        if group_counts[0][i as usize] > 0 &&
-           group_counts[1][i as usize] > 0 &&
-           group_counts[2][i as usize] > 0 {
-          badge_priority += item_priority(i as char) as u64;
-        }
-      }
-      group_counts = [[0 ; 256] ; 3];
-      group_index = 0;
+impl Rucksack {
+  fn parse(items : &str, line : usize) -> Result<Rucksack, RucksackError> {
+    // Every valid item is a single byte, so an invalid item at an odd byte
+    // offset is reported before the length check can trip over it.
+    if let Some(item) = items.chars().find(|item| item_priority(*item).is_none()) {
+      return Err(RucksackError::InvalidItem { line, item });
+    }
+
+    let len = items.len();
+    if !len.is_multiple_of(2) {
+      return Err(RucksackError::OddLength { line, len });
     }
+
+    Ok(Rucksack {
+      compartments : [
+        ItemSet::parse(&items[..len/2], line)?,
+        ItemSet::parse(&items[len/2..], line)?,
+      ],
+    })
   }
 
-  // Expect no leftover lines, must be 3 per group.
-  assert_eq!(group_index, 0);
+  // Items that appear in both compartments.
+  fn shared(&self) -> ItemSet {
+    self.compartments[0].intersect(self.compartments[1])
+  }
 
-  (priority, badge_priority)
+  // Every item in the rucksack.
+  fn items(&self) -> ItemSet {
+    self.compartments[0].union(self.compartments[1])
+  }
 }
 
-fn main() {
-  let (priority, badge_priority) = sum_priorities(io::stdin().lines());
+// Items carried by every rucksack in the group.
+fn group_badge(group : &[Rucksack]) -> ItemSet {
+  group.iter().fold(ItemSet::ALL, |badge, rucksack| badge.intersect(rucksack.items()))
+}
+
+fn parse_rucksacks(lines : impl Iterator<Item = io::Result<String>>)
+    -> Result<Vec<Rucksack>, Box<dyn Error>> {
+  let mut rucksacks = Vec::new();
+  for (idx, line) in lines.enumerate() {
+    rucksacks.push(Rucksack::parse(&line?, idx + 1)?);
+  }
+  Ok(rucksacks)
+}
+
+fn sum_priorities(rucksacks : &[Rucksack], group_size : usize) -> Result<(u64, u64), RucksackError> {
+  assert!(group_size > 0);
+
+  // Expect no leftover lines, must be group_size per group.
+  let leftover = rucksacks.len() % group_size;
+  if leftover != 0 {
+    return Err(RucksackError::IncompleteGroup { group_size, leftover });
+  }
+
+  let priority = rucksacks.iter()
+    .map(|rucksack| rucksack.shared().priority_sum())
+    .sum();
+
+  let badge_priority = rucksacks.chunks(group_size)
+    .map(|group| group_badge(group).priority_sum())
+    .sum();
+
+  Ok((priority, badge_priority))
+}
+
+// Flags a set that doesn't hold exactly one item, which the puzzle expects.
+fn warning(items : ItemSet) -> &'static str {
+  match items.len() {
+    0 => "  <-- no shared item",
+    1 => "",
+    _ => "  <-- multiple shared items",
+  }
+}
+
+// Prints which item was counted for each rucksack and each group.
+fn explain(rucksacks : &[Rucksack], group_size : usize) {
+  for (idx, rucksack) in rucksacks.iter().enumerate() {
+    let shared = rucksack.shared();
+    println!("line {}: shared {}{}", idx + 1, shared, warning(shared));
+  }
+
+  for (idx, group) in rucksacks.chunks(group_size).enumerate() {
+    let first_line = idx * group_size + 1;
+    let last_line = first_line + group.len() - 1;
+    if group.len() < group_size {
+      println!("group {} (lines {}-{}): incomplete, {} of {} rucksacks",
+        idx + 1, first_line, last_line, group.len(), group_size);
+      continue;
+    }
+    let badge = group_badge(group);
+    println!("group {} (lines {}-{}): badge {}{}",
+      idx + 1, first_line, last_line, badge, warning(badge));
+  }
+}
+
+struct Options {
+  group_size : usize,
+  explain : bool,
+}
+
+fn parse_args() -> Result<Options, Box<dyn Error>> {
+  let mut group_size = DEFAULT_GROUP_SIZE;
+  let mut explain = false;
+  let mut args = env::args().skip(1);
+  while let Some(arg) = args.next() {
+    match arg.as_str() {
+      "--group-size" => {
+        group_size = args.next().ok_or("--group-size requires a value")?.parse::<usize>()?;
+        if group_size == 0 {
+          return Err("--group-size must be at least 1".into());
+        }
+      },
+      "--explain" => explain = true,
+      _ => return Err(format!("unexpected argument: {}", arg).into()),
+    }
+  }
+  Ok(Options { group_size, explain })
+}
+
+fn main() -> Result<(), Box<dyn Error>> {
+  let options = parse_args()?;
+  let rucksacks = parse_rucksacks(io::stdin().lines())?;
+  if options.explain {
+    explain(&rucksacks, options.group_size);
+  }
+  let (priority, badge_priority) = sum_priorities(&rucksacks, options.group_size)?;
   println!("part 1: {}", priority);
   println!("part 2: {}", badge_priority);
+  Ok(())
 }
@@ -0,0 +1,79 @@
+use std::collections::BTreeMap;
+
+use crate::interval::{Interval, IntervalSet};
+
+pub struct Coverage {
+  // Section IDs between 1 (or the lowest assigned ID) and the highest
+  // assigned ID that no elf covers.
+  pub uncovered : IntervalSet,
+  // Number of section IDs covered by exactly N elves, keyed by N.
+  pub histogram : BTreeMap<usize, usize>,
+  // (line index, position on the line) of every elf whose sections are all
+  // covered by someone else too.
+  pub redundant : Vec<(usize, usize)>,
+}
+
+// Sweeps the start/end events of all assignments once, so this runs in
+// O(n log n) for n assignments regardless of how wide the ranges are.
+pub fn analyze(groups : &[Vec<Interval>]) -> Coverage {
+  let mut events : Vec<(usize, isize)> = Vec::new();
+  for interval in groups.iter().flatten() {
+    events.push((interval.start(), 1));
+    if let Some(after_end) = interval.end().checked_add(1) {
+      events.push((after_end, -1));
+    }
+  }
+  events.sort_unstable();
+
+  let mut histogram = BTreeMap::new();
+  let mut uncovered = Vec::new();
+  let mut covered_once = Vec::new();
+
+  let first = events.first().map_or(1, |(pos, _)| (*pos).min(1));
+  let mut pos = first;
+  let mut multiplicity = 0isize;
+  let mut idx = 0;
+
+  while idx < events.len() {
+    let next_pos = events[idx].0;
+    if next_pos > pos {
+      // Sections pos..next_pos all share the same multiplicity.
+      let segment = Interval::new(pos, next_pos - 1).unwrap();
+      *histogram.entry(multiplicity as usize).or_insert(0) += segment.len();
+      match multiplicity {
+        0 => uncovered.push(segment),
+        1 => covered_once.push(segment),
+        _ => (),
+      }
+      pos = next_pos;
+    }
+    while idx < events.len() && events[idx].0 == next_pos {
+      multiplicity += events[idx].1;
+      idx += 1;
+    }
+  }
+
+  // An assignment ending at usize::MAX has no closing event.
+  if multiplicity > 0 {
+    let segment = Interval::new(pos, usize::MAX).unwrap();
+    *histogram.entry(multiplicity as usize).or_insert(0) += segment.len();
+    if multiplicity == 1 {
+      covered_once.push(segment);
+    }
+  }
+
+  let uncovered : IntervalSet = uncovered.into_iter().collect();
+  let covered_once : IntervalSet = covered_once.into_iter().collect();
+
+  // An elf is redundant if none of its sections are covered by it alone.
+  let mut redundant = Vec::new();
+  for (group_idx, group) in groups.iter().enumerate() {
+    for (elf_idx, interval) in group.iter().enumerate() {
+      if !covered_once.overlaps(interval) {
+        redundant.push((group_idx, elf_idx));
+      }
+    }
+  }
+
+  Coverage { uncovered, histogram, redundant }
+}
@@ -0,0 +1,205 @@
+// Interval algebra over section IDs. Not every operation is needed by the
+// puzzle answers themselves.
+#![allow(dead_code)]
+
+use std::cmp;
+use std::error::Error;
+use std::fmt;
+use std::str::FromStr;
+
+// Inclusive range of section IDs, start <= end.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Interval {
+  start : usize,
+  end : usize,
+}
+
+impl Interval {
+  pub fn new(start : usize, end : usize) -> Option<Interval> {
+    if start <= end {
+      Some(Interval { start, end })
+    } else {
+      None
+    }
+  }
+
+  pub fn start(&self) -> usize {
+    self.start
+  }
+
+  pub fn end(&self) -> usize {
+    self.end
+  }
+
+  pub fn len(&self) -> usize {
+    self.end - self.start + 1
+  }
+
+  pub fn intersection(&self, other : &Interval) -> Option<Interval> {
+    Interval::new(cmp::max(self.start, other.start), cmp::min(self.end, other.end))
+  }
+}
+
+impl fmt::Display for Interval {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(f, "{}-{}", self.start, self.end)
+  }
+}
+
+impl FromStr for Interval {
+  type Err = Box<dyn Error>;
+
+  fn from_str(s : &str) -> Result<Interval, Self::Err> {
+    let (start, end) = s.split_once('-').ok_or(format!("expected a range like 2-4, got {:?}", s))?;
+    let start = start.parse::<usize>()?;
+    let end = end.parse::<usize>()?;
+    Interval::new(start, end).ok_or(format!("range start is after its end: {:?}", s).into())
+  }
+}
+
+// Set of section IDs stored as sorted, disjoint, non-adjacent intervals.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct IntervalSet {
+  intervals : Vec<Interval>,
+}
+
+impl IntervalSet {
+  pub fn new() -> IntervalSet {
+    IntervalSet { intervals : Vec::new() }
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.intervals.is_empty()
+  }
+
+  pub fn intervals(&self) -> &[Interval] {
+    &self.intervals
+  }
+
+  // Number of section IDs covered.
+  pub fn len(&self) -> usize {
+    self.intervals.iter().map(|interval| interval.len()).sum()
+  }
+
+  pub fn union(&self, other : &IntervalSet) -> IntervalSet {
+    let mut merged : Vec<Interval> = Vec::with_capacity(self.intervals.len() + other.intervals.len());
+    let mut lhs = self.intervals.iter().peekable();
+    let mut rhs = other.intervals.iter().peekable();
+
+    loop {
+      let next = match (lhs.peek(), rhs.peek()) {
+        (Some(a), Some(b)) => if a.start <= b.start { lhs.next() } else { rhs.next() },
+        (Some(_), None) => lhs.next(),
+        (None, Some(_)) => rhs.next(),
+        (None, None) => break,
+      };
+      let next = *next.unwrap();
+
+      match merged.last_mut() {
+        // Overlapping or adjacent, extend the previous interval.
+        Some(last) if next.start <= last.end.saturating_add(1) => {
+          last.end = cmp::max(last.end, next.end);
+        },
+        _ => merged.push(next),
+      }
+    }
+
+    IntervalSet { intervals : merged }
+  }
+
+  pub fn intersection(&self, other : &IntervalSet) -> IntervalSet {
+    let mut result = Vec::new();
+    let (mut i, mut j) = (0, 0);
+
+    while i < self.intervals.len() && j < other.intervals.len() {
+      let a = &self.intervals[i];
+      let b = &other.intervals[j];
+      if let Some(common) = a.intersection(b) {
+        result.push(common);
+      }
+      // Advance whichever interval ends first; it can't meet anything later.
+      if a.end < b.end {
+        i += 1;
+      } else {
+        j += 1;
+      }
+    }
+
+    IntervalSet { intervals : result }
+  }
+
+  pub fn difference(&self, other : &IntervalSet) -> IntervalSet {
+    let mut result = Vec::new();
+    let mut j = 0;
+
+    for a in &self.intervals {
+      let mut start = a.start;
+      let mut remaining = true;
+
+      // Skip intervals that end before this one starts.
+      while j < other.intervals.len() && other.intervals[j].end < a.start {
+        j += 1;
+      }
+
+      let mut k = j;
+      while k < other.intervals.len() && other.intervals[k].start <= a.end {
+        let b = &other.intervals[k];
+        if b.start > start {
+          result.push(Interval { start, end : b.start - 1 });
+        }
+        if b.end >= a.end {
+          remaining = false;
+          break;
+        }
+        start = b.end + 1;
+        k += 1;
+      }
+
+      if remaining {
+        result.push(Interval { start, end : a.end });
+      }
+    }
+
+    IntervalSet { intervals : result }
+  }
+
+  // True if any section ID in interval is also in self, in O(log n).
+  pub fn overlaps(&self, interval : &Interval) -> bool {
+    let idx = self.intervals.partition_point(|candidate| candidate.end < interval.start);
+    idx < self.intervals.len() && self.intervals[idx].start <= interval.end
+  }
+
+  // True if every section ID in other is also in self.
+  pub fn contains(&self, other : &IntervalSet) -> bool {
+    other.difference(self).is_empty()
+  }
+}
+
+impl From<Interval> for IntervalSet {
+  fn from(interval : Interval) -> IntervalSet {
+    IntervalSet { intervals : vec![interval] }
+  }
+}
+
+impl FromIterator<Interval> for IntervalSet {
+  fn from_iter<I : IntoIterator<Item = Interval>>(iter : I) -> IntervalSet {
+    let mut intervals : Vec<Interval> = iter.into_iter().collect();
+    intervals.sort();
+
+    // Sorted input merges in a single pass.
+    let sorted = IntervalSet { intervals };
+    IntervalSet::new().union(&sorted)
+  }
+}
+
+impl fmt::Display for IntervalSet {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    for (idx, interval) in self.intervals.iter().enumerate() {
+      if idx > 0 {
+        write!(f, ",")?;
+      }
+      write!(f, "{}", interval)?;
+    }
+    fmt::Result::Ok(())
+  }
+}
@@ -1,58 +1,93 @@
+use std::env;
+use std::error::Error;
 use std::io;
 
-fn parse_ranges(lines : impl Iterator<Item = io::Result<String>>) -> Vec<[(usize, usize); 2]>{
-  let mut range_pairs : Vec<[(usize, usize); 2]> = Vec::new();
-  range_pairs.push([(1, 2), (3, 4)]);
+mod coverage;
+mod interval;
 
-  for line in lines {
-    let line = line.unwrap();
-    let ranges : Vec<&str> = line.split(',').collect();
-    assert_eq!(ranges.len(), 2); // Always only two entries.
+use interval::{Interval, IntervalSet};
 
-    let mut entry : [(usize, usize); 2] = [(0, 0); 2];
-    for i in 0..2 {
-      let parts : Vec<&str> = ranges[i].split('-').collect();
-      assert_eq!(parts.len(), 2); // Always only two entries.
-
-      entry[i] = (parts[0].parse::<usize>().unwrap(),
-                  parts[1].parse::<usize>().unwrap());
-      assert!(entry[i].0 <= entry[i].1); // Range must be valid.
+// Each line lists the section assignments of one group of elves.
+fn parse_ranges(lines : impl Iterator<Item = io::Result<String>>)
+    -> Result<Vec<Vec<Interval>>, Box<dyn Error>> {
+  let mut groups : Vec<Vec<Interval>> = Vec::new();
 
+  for (idx, line) in lines.enumerate() {
+    let line = line?;
+    let mut group = Vec::new();
+    for range in line.split(',') {
+      let interval = range.trim().parse::<Interval>()
+        .map_err(|err| format!("line {}: {}", idx + 1, err))?;
+      group.push(interval);
     }
-    range_pairs.push(entry);
+    groups.push(group);
   }
 
-  range_pairs
+  Ok(groups)
+}
+
+// Counts groups where at least one assignment is covered by another one.
+fn count_fully_contained_range_pairs(groups : &[Vec<Interval>]) -> usize {
+  groups.iter().filter(|group| {
+    let sets : Vec<IntervalSet> = group.iter().map(|interval| IntervalSet::from(*interval)).collect();
+    (0..sets.len()).any(|i| (0..sets.len()).any(|j| i != j && sets[j].contains(&sets[i])))
+  }).count()
 }
 
-fn count_fully_contained_range_pairs(range_pairs : &Vec<[(usize, usize); 2]>) -> usize {
-  let mut count = 0usize;
-  for entry in range_pairs {
-    if (entry[0].0 >= entry[1].0 && entry[0].1 <= entry[1].1) ||
-       (entry[1].0 >= entry[0].0 && entry[1].1 <= entry[0].1) {
-      count += 1;
+// Counts groups where at least two assignments share a section.
+fn count_overlapping_range_pairs(groups : &[Vec<Interval>]) -> usize {
+  groups.iter().filter(|group| {
+    let mut covered = IntervalSet::new();
+    for interval in group.iter() {
+      let set = IntervalSet::from(*interval);
+      if !covered.intersection(&set).is_empty() {
+        return true;
+      }
+      covered = covered.union(&set);
     }
+    false
+  }).count()
+}
+
+fn print_analysis(groups : &[Vec<Interval>]) {
+  let coverage = coverage::analyze(groups);
+
+  if coverage.uncovered.is_empty() {
+    println!("uncovered sections: none");
+  } else {
+    println!("uncovered sections: {} ({} total)", coverage.uncovered, coverage.uncovered.len());
   }
 
-  count
+  println!("coverage histogram:");
+  for (multiplicity, sections) in &coverage.histogram {
+    println!("  covered {}x: {} sections", multiplicity, sections);
+  }
+
+  println!("redundant elves: {}", coverage.redundant.len());
+  for (group_idx, elf_idx) in &coverage.redundant {
+    println!("  line {}, elf {}: {}", group_idx + 1, elf_idx + 1, groups[*group_idx][*elf_idx]);
+  }
 }
 
-fn count_overlapping_range_pairs(range_pairs : &Vec<[(usize, usize); 2]>) -> usize {
-  let mut count = 0usize;
-  for entry in range_pairs {
-    if entry[0].0 <= entry[1].1 && entry[1].0 <= entry[0].1 {
-      count += 1;
+fn main() -> Result<(), Box<dyn Error>> {
+  let mut analyze = false;
+  for arg in env::args().skip(1) {
+    match arg.as_str() {
+      "--analyze" => analyze = true,
+      _ => return Err(format!("unexpected argument: {}", arg).into()),
     }
   }
 
-  count
-}
+  let groups = parse_ranges(io::stdin().lines())?;
+  if analyze {
+    print_analysis(&groups);
+  }
 
-fn main() {
-  let range_pairs = parse_ranges(io::stdin().lines());
-  let count = count_fully_contained_range_pairs(&range_pairs);
+  let count = count_fully_contained_range_pairs(&groups);
   println!("part 1: {}", count);
 
-  let count = count_overlapping_range_pairs(&range_pairs);
+  let count = count_overlapping_range_pairs(&groups);
   println!("part 2: {}", count);
+
+  Ok(())
 }
@@ -1,108 +1,480 @@
 use std::cmp;
+use std::env;
+use std::error::Error;
+use std::fmt;
+use std::fs::File;
 use std::io;
+use std::io::BufRead;
+use std::str::FromStr;
 
-fn pretty_print_stacks(stacks : &Vec<Vec<char>>) {
-  // Determine the deepest stack for an upper bound on printing.
-  let mut max_rows = 0;
-  for stack in stacks {
-    max_rows = cmp::max(max_rows, stack.len());
-  }
+mod planner;
 
-  for row_idx in (0..max_rows).rev() {
-    for col_idx in 0..stacks.len() {
-      assert!(stacks[col_idx].len() <= max_rows);
-      if row_idx < stacks[col_idx].len() {
-        print!("[{}] ", stacks[col_idx][row_idx]);
-      } else {
-        print!("    ");
-      }
+// Stacks of crates, bottom first. Crate labels may be several chars wide.
+type Stacks = Vec<Vec<String>>;
+
+// Index row positions (first char, last char) of each stack number.
+fn parse_index_row(line : &[char]) -> Result<Vec<(usize, usize)>, Box<dyn Error>> {
+  let mut columns = Vec::new();
+  let mut pos = 0;
+  while pos < line.len() {
+    if line[pos] == ' ' {
+      pos += 1;
+      continue;
+    }
+    let start = pos;
+    while pos < line.len() && line[pos] != ' ' {
+      pos += 1;
     }
-    println!("");
+    let token : String = line[start..pos].iter().collect();
+    let number = token.parse::<usize>()
+      .map_err(|_| format!("index row: expected a stack number, got {:?}", token))?;
+    if number != columns.len() + 1 {
+      return Err(format!("index row: expected stack {}, got {}", columns.len() + 1, number).into());
+    }
+    columns.push((start, pos - 1));
+  }
+
+  if columns.is_empty() {
+    return Err("index row: no stacks".into());
   }
+  Ok(columns)
 }
 
-fn parse_stacks(lines : &mut impl Iterator<Item = io::Result<String>>) -> Vec<Vec<char>> {
+fn parse_stacks(lines : &mut impl Iterator<Item = io::Result<String>>) -> Result<Stacks, Box<dyn Error>> {
   // Consume lines until we hit a blank line, indicating the end of the stack
   // diagram portion of the input.
   let mut stack_lines = Vec::new();
   for line in lines {
-    let line = line.unwrap();
-    if line.len() == 0 {
+    let line = line?;
+    if line.is_empty() {
       break;
     }
     stack_lines.push(line);
   }
 
-  // Last line of the stack diagram tells us how many stacks there are.
-  let line = stack_lines.pop().unwrap();
-  let parts : Vec<&str> = line.split_whitespace().collect();
-  let stack_count = parts.len();
+  // Last line of the stack diagram tells us how many stacks there are, and
+  // where each stack's column sits.
+  let line = stack_lines.pop().ok_or("missing stack diagram")?;
+  let columns = parse_index_row(&line.chars().collect::<Vec<char>>())?;
+  let mut stacks : Stacks = vec![Vec::new(); columns.len()];
 
-  let mut stacks = Vec::with_capacity(stack_count);
-  for _i in 0..stack_count {
-    stacks.push(Vec::new());
-  }
-
-  // Convert stack lines into a vector of stacks.
-  const CHARS_PER_ITEM : usize = 4usize;
-  for line in stack_lines.iter().rev() {
+  // Convert stack lines into a vector of stacks. A crate belongs to the stack
+  // whose number sits under its brackets.
+  for (depth, line) in stack_lines.iter().rev().enumerate() {
+    let line_num = stack_lines.len() - depth;
     let chars : Vec<char> = line.chars().collect();
-    for i in (0..chars.len()).step_by(CHARS_PER_ITEM) {
-      let item : char = chars[i + 1];
-      if item == ' ' {
-        continue;
-      }
+    let mut pos = 0;
+    while pos < chars.len() {
+      match chars[pos] {
+        ' ' => pos += 1,
+        '[' => {
+          let close = chars[pos..].iter().position(|ch| *ch == ']')
+            .ok_or(format!("line {}: unterminated crate at column {}", line_num, pos + 1))? + pos;
+          let label : String = chars[pos + 1..close].iter().collect();
+          if label.is_empty() {
+            return Err(format!("line {}: empty crate at column {}", line_num, pos + 1).into());
+          }
 
-      stacks[i / CHARS_PER_ITEM].push(item);
+          let stack_idx = columns.iter()
+            .position(|(start, end)| pos <= *start && *end <= close)
+            .ok_or(format!("line {}: crate [{}] isn't above a stack number", line_num, label))?;
+          if stacks[stack_idx].len() != depth {
+            return Err(format!("line {}: crate [{}] is floating above stack {}",
+              line_num, label, stack_idx + 1).into());
+          }
+
+          stacks[stack_idx].push(label);
+          pos = close + 1;
+        },
+        ch => return Err(format!("line {}: unexpected {:?} at column {}", line_num, ch, pos + 1).into()),
+      }
     }
   }
 
-  stacks
+  Ok(stacks)
 }
 
-fn execute_actions(stacks : &mut Vec<Vec<char>>, lines : &mut impl Iterator<Item = io::Result<String>>) {
-  for line in lines {
-    let line = line.unwrap();
+// Centers text in a cell of the given width, leaning left. Crates and stack
+// numbers both go through this so that a number always ends up under its
+// crates' brackets, however their widths differ.
+fn center(text : &str, width : usize) -> String {
+  let left = (width - text.chars().count()) / 2;
+  format!("{}{:<width$}", " ".repeat(left), text, width = width - left)
+}
+
+// Formats the stacks as a diagram parse_stacks reads back unchanged,
+// including the index row. Every column is as wide as the widest crate or
+// stack number, and every row is padded to the full width.
+fn format_stacks(stacks : &[Vec<String>]) -> String {
+  let widest_crate = stacks.iter().flatten().map(|label| label.chars().count() + 2).max().unwrap_or(3);
+  let widest_index = stacks.len().to_string().len();
+  let width = cmp::max(widest_crate, widest_index);
+  let max_rows = stacks.iter().map(|stack| stack.len()).max().unwrap_or(0);
+
+  let mut result = String::new();
+  for row_idx in (0..max_rows).rev() {
+    let cells : Vec<String> = stacks.iter().map(|stack| match stack.get(row_idx) {
+      Some(label) => center(&format!("[{}]", label), width),
+      None => " ".repeat(width),
+    }).collect();
+    result.push_str(&cells.join(" "));
+    result.push('\n');
+  }
+
+  let cells : Vec<String> = (1..=stacks.len()).map(|number| center(&number.to_string(), width)).collect();
+  result.push_str(&cells.join(" "));
+  result.push('\n');
+
+  result
+}
+
+fn pretty_print_stacks(stacks : &[Vec<String>]) {
+  print!("{}", format_stacks(stacks));
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Move {
+  count : usize,
+  source_idx : usize,
+  dest_idx : usize,
+}
+
+// Formats the move the way it appears in the input.
+impl fmt::Display for Move {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(f, "move {} from {} to {}", self.count, self.source_idx + 1, self.dest_idx + 1)
+  }
+}
+
+impl FromStr for Move {
+  type Err = Box<dyn Error>;
+
+  fn from_str(line : &str) -> Result<Move, Self::Err> {
     let parts : Vec<&str> = line.split_whitespace().collect();
-    assert_eq!(parts.len(), 6);
+    let parse_number = |idx : usize| parts[idx].parse::<usize>()
+      .map_err(|_| format!("{:?} isn't a number", parts[idx]));
+
+    if parts.len() != 6 || parts[0] != "move" || parts[2] != "from" || parts[4] != "to" {
+      return Err("expected \"move N from A to B\"".into());
+    }
+
+    let (count, source, dest) = (parse_number(1)?, parse_number(3)?, parse_number(5)?);
+    if source == 0 || dest == 0 {
+      return Err("stack numbers start at 1".into());
+    }
+
+    Ok(Move { count, source_idx : source - 1, dest_idx : dest - 1 })
+  }
+}
+
+// Moves start on input line first_line_num, which errors refer to.
+fn parse_moves(lines : &mut impl Iterator<Item = io::Result<String>>, first_line_num : usize)
+    -> Result<Vec<Move>, Box<dyn Error>> {
+  let mut moves = Vec::new();
+  for line in lines {
+    let line = line?;
+    let mv = line.parse::<Move>()
+      .map_err(|err| format!("line {} ({:?}): {}", first_line_num + moves.len(), line, err))?;
+    moves.push(mv);
+  }
+
+  Ok(moves)
+}
+
+// Checks that mv can be carried out on stacks.
+fn validate_move(stacks : &[Vec<String>], mv : &Move) -> Result<(), String> {
+  for idx in [mv.source_idx, mv.dest_idx] {
+    if idx >= stacks.len() {
+      return Err(format!("there is no stack {}, only {}", idx + 1, stacks.len()));
+    }
+  }
+
+  let available = stacks[mv.source_idx].len();
+  if mv.count > available {
+    return Err(format!("stack {} holds only {} crates", mv.source_idx + 1, available));
+  }
+
+  Ok(())
+}
+
+trait Crane {
+  fn name(&self) -> &'static str;
+  fn move_crates(&self, stacks : &mut [Vec<String>], mv : &Move);
+}
+
+// Moves crates one at a time, reversing their order.
+struct CrateMover9000;
+
+impl Crane for CrateMover9000 {
+  fn name(&self) -> &'static str {
+    "CrateMover 9000"
+  }
+
+  fn move_crates(&self, stacks : &mut [Vec<String>], mv : &Move) {
+    for _i in 0..mv.count {
+      let item = stacks[mv.source_idx].pop().unwrap();
+      stacks[mv.dest_idx].push(item);
+    }
+  }
+}
+
+// Moves crates all at once, preserving their order.
+struct CrateMover9001;
+
+impl Crane for CrateMover9001 {
+  fn name(&self) -> &'static str {
+    "CrateMover 9001"
+  }
+
+  fn move_crates(&self, stacks : &mut [Vec<String>], mv : &Move) {
+    let source = &mut stacks[mv.source_idx];
+    let items = source.split_off(source.len() - mv.count);
+    stacks[mv.dest_idx].extend(items);
+  }
+}
 
-    let (count, source_idx, dest_idx)  = (
-        parts[1].parse::<usize>().unwrap(),
-        parts[3].parse::<usize>().unwrap() - 1,
-        parts[5].parse::<usize>().unwrap() - 1);
-    assert!(source_idx < stacks.len());
-    assert!(dest_idx < stacks.len());
+fn execute_actions(stacks : &mut [Vec<String>], moves : &[Move], first_line_num : usize, crane : &dyn Crane)
+    -> Result<(), Box<dyn Error>> {
+  for (idx, mv) in moves.iter().enumerate() {
+    validate_move(stacks, mv).map_err(|err| format!("line {} ({}): {}", first_line_num + idx, mv, err))?;
+    crane.move_crates(stacks, mv);
+  }
 
-    let mut tmp_stack = Vec::with_capacity(count);
-    for _i in 0..count {
-      let item = stacks[source_idx].pop().unwrap();
-      tmp_stack.push(item);
+  Ok(())
+}
+
+// Steps through a list of moves, keeping the state after every executed
+// move so that it can step back and forth without re-running anything.
+struct Replay<'a> {
+  crane : &'a dyn Crane,
+  moves : &'a [Move],
+  // Input line of the first move, for errors.
+  first_line_num : usize,
+  // history[n] is the state after the first n moves.
+  history : Vec<Stacks>,
+  position : usize,
+}
+
+impl<'a> Replay<'a> {
+  fn new(stacks : Stacks, moves : &'a [Move], first_line_num : usize, crane : &'a dyn Crane) -> Replay<'a> {
+    Replay { crane, moves, first_line_num, history : vec![stacks], position : 0 }
+  }
+
+  // Number of moves applied to reach the current state.
+  fn position(&self) -> usize {
+    self.position
+  }
+
+  fn state(&self) -> &Stacks {
+    &self.history[self.position]
+  }
+
+  // Applies the next move, returns false if there are none left.
+  fn step_forward(&mut self) -> Result<bool, Box<dyn Error>> {
+    if self.position == self.moves.len() {
+      return Ok(false);
+    }
+
+    if self.position + 1 == self.history.len() {
+      let mv = &self.moves[self.position];
+      let mut stacks = self.state().clone();
+      validate_move(&stacks, mv)
+        .map_err(|err| format!("line {} ({}): {}", self.first_line_num + self.position, mv, err))?;
+      self.crane.move_crates(&mut stacks, mv);
+      self.history.push(stacks);
     }
 
-    for _i in 0..count {
-      let item = tmp_stack.pop().unwrap();
-      stacks[dest_idx].push(item);
+    self.position += 1;
+    Ok(true)
+  }
+
+  // Undoes the last move, returns false if already at the start.
+  fn step_back(&mut self) -> bool {
+    if self.position == 0 {
+      return false;
     }
+    self.position -= 1;
+    true
+  }
+
+  // Moves to the state after the first n moves.
+  fn seek(&mut self, n : usize) -> Result<(), Box<dyn Error>> {
+    if n > self.moves.len() {
+      return Err(format!("there are only {} moves", self.moves.len()).into());
+    }
+    while self.position > n {
+      self.step_back();
+    }
+    while self.position < n {
+      self.step_forward()?;
+    }
+    Ok(())
+  }
+
+  // First move after which a crate labelled label is on top of a stack.
+  fn first_on_top(&mut self, label : &str) -> Result<Option<usize>, Box<dyn Error>> {
+    self.seek(0)?;
+    while self.step_forward()? {
+      if self.state().iter().any(|stack| stack.last().is_some_and(|top| top == label)) {
+        return Ok(Some(self.position()));
+      }
+    }
+    Ok(None)
   }
 }
 
-fn main() {
+fn top_crates(stacks : &[Vec<String>]) -> String {
+  stacks.iter().filter_map(|stack| stack.last().map(String::as_str)).collect()
+}
+
+fn crane_for_model(model : &str) -> Option<&'static dyn Crane> {
+  match model {
+    "9000" => Some(&CrateMover9000),
+    "9001" => Some(&CrateMover9001),
+    _ => None,
+  }
+}
+
+struct Options {
+  after : Option<usize>,
+  first_on_top : Option<String>,
+  plan_goal : Option<String>,
+  crane : Option<&'static dyn Crane>,
+}
+
+fn parse_args() -> Result<Options, Box<dyn Error>> {
+  let mut options = Options { after : None, first_on_top : None, plan_goal : None, crane : None };
+  let mut args = env::args().skip(1);
+  while let Some(arg) = args.next() {
+    match arg.as_str() {
+      "--after" => {
+        options.after = Some(args.next().ok_or("--after requires a move number")?.parse::<usize>()?);
+      },
+      "--first-on-top" => {
+        options.first_on_top = Some(args.next().ok_or("--first-on-top requires a crate label")?);
+      },
+      "--plan" => {
+        options.plan_goal = Some(args.next().ok_or("--plan requires a goal diagram file")?);
+      },
+      "--crane" => {
+        let model = args.next().ok_or("--crane requires a model")?;
+        options.crane = Some(crane_for_model(&model).ok_or(format!("unknown crane model: {}", model))?);
+      },
+      _ => return Err(format!("unexpected argument: {}", arg).into()),
+    }
+  }
+  Ok(options)
+}
+
+// Prints day5 input that moves the crates from the start diagram on stdin to
+// the goal diagram in goal_path.
+fn print_plan(stacks : &Stacks, goal_path : &str, crane : &dyn Crane) -> Result<(), Box<dyn Error>> {
+  let goal = parse_stacks(&mut io::BufReader::new(File::open(goal_path)?).lines())?;
+  let moves = planner::plan(stacks, &goal, crane)?;
+
+  // Double check the plan before handing it out. Line numbers are the ones
+  // in the printed plan, after the diagram and a blank line.
+  let diagram = format_stacks(stacks);
+  let mut end_state = stacks.clone();
+  execute_actions(&mut end_state, &moves, diagram.lines().count() + 2, crane)?;
+  assert_eq!(end_state, goal);
+
+  print!("{}", diagram);
+  println!();
+  for mv in &moves {
+    println!("{}", mv);
+  }
+  Ok(())
+}
+
+fn main() -> Result<(), Box<dyn Error>> {
+  let options = parse_args()?;
   let mut lines = io::stdin().lines();
-  let mut stacks = parse_stacks(&mut lines);
+  // The diagram and the blank line after it.
+  let mut diagram_line_count = 0;
+  let stacks = parse_stacks(&mut lines.by_ref().inspect(|_| diagram_line_count += 1))?;
+  let first_move_line_num = diagram_line_count + 1;
+
+  if let Some(goal_path) = &options.plan_goal {
+    let crane = options.crane.ok_or("--plan requires --crane 9000 or 9001")?;
+    return print_plan(&stacks, goal_path, crane);
+  }
+  let moves = parse_moves(&mut lines, first_move_line_num)?;
 
   println!("Start state:");
   pretty_print_stacks(&stacks);
 
-  execute_actions(&mut stacks, &mut lines);
+  let cranes : [&dyn Crane; 2] = [&CrateMover9000, &CrateMover9001];
+  for (part, crane) in cranes.iter().enumerate() {
+    let mut replay = Replay::new(stacks.clone(), &moves, first_move_line_num, *crane);
 
-  println!("End state:");
-  pretty_print_stacks(&stacks);
+    if let Some(label) = &options.first_on_top {
+      match replay.first_on_top(label)? {
+        Some(n) => println!("[{}] first reaches the top after move {} ({}) with the {}",
+          label, n, moves[n - 1], crane.name()),
+        None => println!("[{}] never reaches the top with the {}", label, crane.name()),
+      }
+    }
 
-  let mut result = String::new();
+    if let Some(n) = options.after {
+      replay.seek(n)?;
+      println!("State after move {} ({}):", n, crane.name());
+      pretty_print_stacks(replay.state());
+    }
+
+    let mut stacks = stacks.clone();
+    execute_actions(&mut stacks, &moves, first_move_line_num, *crane)?;
 
-  for stack in stacks {
-    result.push(*stack.last().unwrap());
+    println!("End state ({}):", crane.name());
+    pretty_print_stacks(&stacks);
+
+    println!("part {}: {}", part + 1, top_crates(&stacks));
+  }
+
+  Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn parse(diagram : &str) -> Stacks {
+    parse_stacks(&mut diagram.lines().map(|line| Ok(line.to_string()))).unwrap()
+  }
+
+  // format -> parse -> format gives back the same stacks and diagram.
+  fn assert_round_trips(stacks : &Stacks) {
+    let diagram = format_stacks(stacks);
+    let parsed = parse(&diagram);
+    assert_eq!(&parsed, stacks, "diagram:\n{}", diagram);
+    assert_eq!(format_stacks(&parsed), diagram);
   }
 
-  println!("part 2: {}", result);
+  fn labels(labels : &[&[&str]]) -> Stacks {
+    labels.iter().map(|stack| stack.iter().map(|label| label.to_string()).collect()).collect()
+  }
+
+  #[test]
+  fn narrow_crate_next_to_wide_one() {
+    let stacks = parse("[A]     [ABCDE]\n 1         2   \n");
+    assert_eq!(stacks, labels(&[&["A"], &["ABCDE"]]));
+    assert_round_trips(&stacks);
+  }
+
+  #[test]
+  fn mixed_label_widths() {
+    assert_round_trips(&labels(&[&["A", "BB"], &["CCCCCC"], &[], &["D", "EEEEEEE", "FF"]]));
+  }
+
+  #[test]
+  fn more_than_nine_stacks() {
+    let stacks : Stacks = (0..12).map(|idx| vec![String::from("X"); idx % 4]).collect();
+    assert_round_trips(&stacks);
+
+    // Every label width from 1 to 8 in every column position.
+    let stacks : Stacks = (0..15)
+      .map(|idx| (0..idx % 5).map(|depth| "W".repeat(1 + (idx + depth) % 8)).collect())
+      .collect();
+    assert_round_trips(&stacks);
+  }
 }
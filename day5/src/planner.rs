@@ -0,0 +1,177 @@
+use std::collections::HashMap;
+use std::collections::VecDeque;
+use std::error::Error;
+
+use crate::{Crane, Move, Stacks};
+
+// Puzzles up to this many crates get an exhaustive search, as long as it
+// visits no more than SEARCH_LIMIT states.
+const SEARCH_MAX_CRATES : usize = 12;
+const SEARCH_LIMIT : usize = 100_000;
+
+// Finds a list of moves that turns start into goal with the given crane.
+// Small puzzles get a shortest plan from a breadth-first search; larger ones
+// get a greedy plan that builds every stack from the bottom up.
+pub fn plan(start : &Stacks, goal : &Stacks, crane : &dyn Crane) -> Result<Vec<Move>, Box<dyn Error>> {
+  if start.len() != goal.len() {
+    return Err(format!("start has {} stacks but goal has {}", start.len(), goal.len()).into());
+  }
+
+  let mut start_crates : Vec<&String> = start.iter().flatten().collect();
+  let mut goal_crates : Vec<&String> = goal.iter().flatten().collect();
+  start_crates.sort();
+  goal_crates.sort();
+  if start_crates != goal_crates {
+    return Err("start and goal don't hold the same crates".into());
+  }
+
+  if start_crates.len() <= SEARCH_MAX_CRATES {
+    if let Some(moves) = search(start, goal, crane) {
+      return Ok(moves);
+    }
+  }
+
+  greedy(start, goal, crane)
+}
+
+// Breadth-first search over every reachable state. Returns None if the goal
+// is unreachable or the search gets too big.
+fn search(start : &Stacks, goal : &Stacks, crane : &dyn Crane) -> Option<Vec<Move>> {
+  // Each visited state maps to the state and move it was reached from.
+  let mut visited : HashMap<Stacks, Option<(Stacks, Move)>> = HashMap::new();
+  let mut queue = VecDeque::new();
+  visited.insert(start.clone(), None);
+  queue.push_back(start.clone());
+
+  while let Some(stacks) = queue.pop_front() {
+    if stacks == *goal {
+      let mut moves = Vec::new();
+      let mut state = stacks;
+      while let Some((prev, mv)) = visited[&state].clone() {
+        moves.push(mv);
+        state = prev;
+      }
+      moves.reverse();
+      return Some(moves);
+    }
+
+    for source_idx in 0..stacks.len() {
+      for dest_idx in 0..stacks.len() {
+        if source_idx == dest_idx {
+          continue;
+        }
+        for count in 1..=stacks[source_idx].len() {
+          let mv = Move { count, source_idx, dest_idx };
+          let mut next = stacks.clone();
+          crane.move_crates(&mut next, &mv);
+          if visited.contains_key(&next) {
+            continue;
+          }
+          if visited.len() == SEARCH_LIMIT {
+            return None;
+          }
+          visited.insert(next.clone(), Some((stacks.clone(), mv)));
+          queue.push_back(next);
+        }
+      }
+    }
+  }
+
+  None
+}
+
+// Number of crates at the bottom of stack that are already where goal wants
+// them. Crates above that are in the way.
+fn settled(stack : &[String], goal : &[String]) -> usize {
+  stack.iter().zip(goal).take_while(|(have, want)| have == want).count()
+}
+
+// Order the crane leaves the top count crates of stack in, bottom first.
+fn lifted(stack : &[String], count : usize, crane : &dyn Crane) -> Vec<String> {
+  let mut scratch = vec![stack[stack.len() - count..].to_vec(), Vec::new()];
+  crane.move_crates(&mut scratch, &Move { count, source_idx : 0, dest_idx : 1 });
+  scratch.pop().unwrap()
+}
+
+// Largest move that puts the next wanted crates straight onto a stack that
+// holds nothing but settled crates.
+fn find_placement(stacks : &Stacks, goal : &Stacks, settled : &[usize], crane : &dyn Crane) -> Option<Move> {
+  let mut best : Option<Move> = None;
+  for dest_idx in 0..stacks.len() {
+    let base = settled[dest_idx];
+    if stacks[dest_idx].len() != base || base == goal[dest_idx].len() {
+      continue;
+    }
+    for source_idx in 0..stacks.len() {
+      let loose = stacks[source_idx].len() - settled[source_idx];
+      if source_idx == dest_idx || loose == 0 {
+        continue;
+      }
+      let wanted = goal[dest_idx].len() - base;
+      for count in (1..=loose.min(wanted)).rev() {
+        if best.is_some_and(|mv| mv.count >= count) {
+          break;
+        }
+        if lifted(&stacks[source_idx], count, crane) == goal[dest_idx][base..base + count] {
+          best = Some(Move { count, source_idx, dest_idx });
+          break;
+        }
+      }
+    }
+  }
+  best
+}
+
+// Stack to put crates that are in the way on, preferring stacks that need
+// clearing anyway and then the ones with the most left to build.
+fn find_dump(stacks : &Stacks, goal : &Stacks, settled : &[usize], exclude : &[usize]) -> usize {
+  (0..stacks.len())
+    .filter(|idx| !exclude.contains(idx))
+    .max_by_key(|idx| (stacks[*idx].len() > settled[*idx], goal[*idx].len() - settled[*idx]))
+    .unwrap()
+}
+
+fn greedy(start : &Stacks, goal : &Stacks, crane : &dyn Crane) -> Result<Vec<Move>, Box<dyn Error>> {
+  if start.len() < 3 {
+    return Err("no plan found, crates can't be set aside with fewer than 3 stacks".into());
+  }
+
+  let mut stacks = start.clone();
+  let mut moves = Vec::new();
+
+  // Every round either settles at least one crate or unblocks a stack so the
+  // next round can, so this always finishes.
+  while stacks != *goal {
+    let settled : Vec<usize> = stacks.iter().zip(goal).map(|(stack, goal)| settled(stack, goal)).collect();
+
+    let mv = if let Some(mv) = find_placement(&stacks, goal, &settled, crane) {
+      mv
+    } else if let Some(dest_idx) = (0..stacks.len())
+        .find(|idx| stacks[*idx].len() == settled[*idx] && settled[*idx] < goal[*idx].len()) {
+      // Dig out the nearest crate the stack needs next.
+      let wanted = &goal[dest_idx][settled[dest_idx]];
+      let (source_idx, count) = (0..stacks.len())
+        .filter(|idx| *idx != dest_idx)
+        .filter_map(|idx| stacks[idx][settled[idx]..].iter().rev()
+          .position(|label| label == wanted)
+          .map(|above| (idx, above)))
+        .min_by_key(|(_, above)| *above)
+        .unwrap();
+      let dump_idx = find_dump(&stacks, goal, &settled, &[source_idx, dest_idx]);
+      Move { count, source_idx, dest_idx : dump_idx }
+    } else {
+      // Every unfinished stack is buried, clear the one with the least on top.
+      let source_idx = (0..stacks.len())
+        .filter(|idx| settled[*idx] < goal[*idx].len())
+        .min_by_key(|idx| stacks[*idx].len() - settled[*idx])
+        .unwrap();
+      let dump_idx = find_dump(&stacks, goal, &settled, &[source_idx]);
+      Move { count : stacks[source_idx].len() - settled[source_idx], source_idx, dest_idx : dump_idx }
+    };
+
+    crane.move_crates(&mut stacks, &mv);
+    moves.push(mv);
+  }
+
+  Ok(moves)
+}
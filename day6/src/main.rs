@@ -1,56 +1,228 @@
+use std::env;
+use std::error::Error;
+use std::fs;
+use std::fs::File;
 use std::io;
+use std::io::Write;
+use std::path::Path;
 
-fn find_start_of_packet_index(packet : &String) -> usize {
-  let packet_chars : Vec<char> = packet.chars().collect();
-  for i in 0..packet.len() {
-    if packet_chars[i + 0] != packet_chars[i + 1] &&
-       packet_chars[i + 0] != packet_chars[i + 2] &&
-       packet_chars[i + 0] != packet_chars[i + 3] &&
-       packet_chars[i + 1] != packet_chars[i + 2] &&
-       packet_chars[i + 1] != packet_chars[i + 3] &&
-       packet_chars[i + 2] != packet_chars[i + 3] {
-      return i + 4;
+mod stream;
+
+use stream::Event;
+
+const START_OF_PACKET_LEN : usize = 4;
+const START_OF_MESSAGE_LEN : usize = 14;
+
+// Watches a byte stream for window_len distinct consecutive bytes. Tracks
+// where each byte was last seen so every byte is visited only once.
+struct MarkerDetector {
+  window_len : u64,
+  // One past the position each byte was last seen at, 0 if never.
+  last_seen : [u64; 256],
+  // Start of the run of distinct bytes ending at the current position.
+  window_start : u64,
+  pos : u64,
+}
+
+impl MarkerDetector {
+  fn new(window_len : usize) -> MarkerDetector {
+    assert!(window_len > 0);
+    MarkerDetector { window_len : window_len as u64, last_seen : [0; 256], window_start : 0, pos : 0 }
+  }
+
+  // Feeds the next byte, returns the position just past it if it completes a
+  // marker. The following marker must start after this one.
+  fn push(&mut self, byte : u8) -> Option<u64> {
+    let seen = self.last_seen[byte as usize];
+    if seen > self.window_start {
+      // Repeated inside the window, restart just after the earlier copy.
+      self.window_start = seen;
+    }
+    self.pos += 1;
+    self.last_seen[byte as usize] = self.pos;
+
+    if self.pos - self.window_start == self.window_len {
+      self.window_start = self.pos;
+      Some(self.pos)
+    } else {
+      None
     }
   }
-  unreachable!();
 }
 
-fn find_start_of_message_index(packet : &String) -> usize {
-  const PACKET_LEN : usize = 14usize;
-  let packet_chars : Vec<char> = packet.chars().collect();
-  for i in 0..packet.len() {
-    // A bit array to track which characters have been seen.
-    let mut seen_chars : [bool; std::char::MAX as usize] = [false; std::char::MAX as usize];
+// Index just past the first window_len distinct consecutive bytes, if any.
+fn find_marker(data : &[u8], window_len : usize) -> Option<usize> {
+  if window_len == 0 {
+    return Some(0);
+  }
 
-    for j in 0..PACKET_LEN {
-      // Must never run off the end of valid input.
-      assert!(i + j < packet_chars.len());
+  let mut detector = MarkerDetector::new(window_len);
+  data.iter().find_map(|byte| detector.push(*byte)).map(|pos| pos as usize)
+}
 
-      let ch = packet_chars[i + j] as usize;
+fn print_marker(part : usize, marker : Option<usize>) {
+  match marker {
+    Some(idx) => println!("part {}: {}", part, idx),
+    None => println!("part {}: no marker found", part),
+  }
+}
 
-      if seen_chars[ch] {
-        // Already seen this character in the range we're looking at.
-        break;
-      }
+// Prints every marker in stdin, and if split_dir is set also writes each
+// message to its own file there.
+fn decode_stdin(options : &Options, split_dir : Option<&Path>) -> Result<(), Box<dyn Error>> {
+  if let Some(dir) = split_dir {
+    fs::create_dir_all(dir)?;
+  }
+
+  let stdout = io::stdout();
+  let mut out = io::BufWriter::new(stdout.lock());
+  let mut message_count = 0;
+  let mut message_file : Option<io::BufWriter<File>> = None;
+
+  stream::decode(&mut io::stdin().lock(), options.packet_len, options.message_len, &mut |event| {
+    match event {
+      Event::StartOfPacket(pos) => writeln!(out, "start-of-packet: {}", pos),
+      Event::StartOfMessage(pos) => {
+        message_count += 1;
+        if let Some(dir) = split_dir {
+          if let Some(mut file) = message_file.take() {
+            file.flush()?;
+          }
+          let path = dir.join(format!("message-{}.bin", message_count));
+          message_file = Some(io::BufWriter::new(File::create(path)?));
+        }
+        writeln!(out, "start-of-message: {}", pos)
+      },
+      Event::MessageData(data) => match message_file.as_mut() {
+        Some(file) => file.write_all(data),
+        None => Ok(()),
+      },
+    }
+  })?;
+
+  if let Some(mut file) = message_file {
+    file.flush()?;
+  }
+  out.flush()?;
+  Ok(())
+}
+
+fn format_marker(marker : Option<usize>) -> String {
+  match marker {
+    Some(idx) => idx.to_string(),
+    None => String::from("none"),
+  }
+}
 
-      seen_chars[ch] = true;
+// Treats every line of stdin as its own datastream and reports both markers
+// for each of them.
+fn batch_stdin(options : &Options, format : Format) -> Result<(), Box<dyn Error>> {
+  let mut results = Vec::new();
+  for line in io::stdin().lines() {
+    let line = line?;
+    let data = line.trim_end().as_bytes();
+    results.push((find_marker(data, options.packet_len), find_marker(data, options.message_len)));
+  }
 
-      if j + 1 == PACKET_LEN {
-        return i + j + 1;
+  match format {
+    Format::Table => {
+      println!("{:>6} {:>8} {:>8}", "line", "packet", "message");
+      for (idx, (packet, message)) in results.iter().enumerate() {
+        println!("{:>6} {:>8} {:>8}", idx + 1, format_marker(*packet), format_marker(*message));
+      }
+    },
+    Format::Json => {
+      let json_marker = |marker : Option<usize>| marker.map_or(String::from("null"), |idx| idx.to_string());
+      println!("[");
+      for (idx, (packet, message)) in results.iter().enumerate() {
+        println!("  {{\"line\": {}, \"packet\": {}, \"message\": {}}}{}",
+          idx + 1, json_marker(*packet), json_marker(*message),
+          if idx + 1 < results.len() { "," } else { "" });
       }
+      println!("]");
+    },
+  }
+
+  Ok(())
+}
+
+#[derive(Clone, Copy)]
+enum Format {
+  Table,
+  Json,
+}
+
+enum Mode {
+  FirstLine,
+  Batch(Format),
+  Stream(Option<String>),
+}
+
+struct Options {
+  mode : Mode,
+  packet_len : usize,
+  message_len : usize,
+}
+
+fn parse_window_len(arg : &str, value : Option<String>) -> Result<usize, Box<dyn Error>> {
+  let len = value.ok_or(format!("{} requires a window size", arg))?.parse::<usize>()?;
+  if len == 0 {
+    return Err(format!("{} must be at least 1", arg).into());
+  }
+  Ok(len)
+}
+
+fn parse_args() -> Result<Options, Box<dyn Error>> {
+  let mut options = Options {
+    mode : Mode::FirstLine,
+    packet_len : START_OF_PACKET_LEN,
+    message_len : START_OF_MESSAGE_LEN,
+  };
+  let mut format = Format::Table;
+  let mut batch = false;
+
+  let mut args = env::args().skip(1);
+  while let Some(arg) = args.next() {
+    match arg.as_str() {
+      "--stream" => options.mode = Mode::Stream(None),
+      "--split" => {
+        options.mode = Mode::Stream(Some(args.next().ok_or("--split requires a directory")?));
+      },
+      "--batch" => batch = true,
+      "--json" => {
+        batch = true;
+        format = Format::Json;
+      },
+      "--packet-len" => options.packet_len = parse_window_len(&arg, args.next())?,
+      "--message-len" => options.message_len = parse_window_len(&arg, args.next())?,
+      _ => return Err(format!("unexpected argument: {}", arg).into()),
     }
   }
 
-  // Never reachable with valid input.
-  unreachable!();
+  if batch {
+    if let Mode::Stream(_) = options.mode {
+      return Err("--batch can't be combined with --stream or --split".into());
+    }
+    options.mode = Mode::Batch(format);
+  }
+  Ok(options)
 }
 
-fn main() {
+fn main() -> Result<(), Box<dyn Error>> {
+  let options = parse_args()?;
+
+  match &options.mode {
+    Mode::Stream(split_dir) => return decode_stdin(&options, split_dir.as_deref().map(Path::new)),
+    Mode::Batch(format) => return batch_stdin(&options, *format),
+    Mode::FirstLine => (),
+  }
+
   let mut input = String::new();
-  io::stdin().read_line(&mut input).unwrap();
-  let result = find_start_of_packet_index(&input);
-  println!("part 1: {}", result);
+  io::stdin().read_line(&mut input)?;
+  let data = input.trim_end().as_bytes();
+
+  print_marker(1, find_marker(data, options.packet_len));
+  print_marker(2, find_marker(data, options.message_len));
 
-  let result = find_start_of_message_index(&input);
-  println!("part 2: {}", result);
+  Ok(())
 }
@@ -0,0 +1,73 @@
+use std::io;
+use std::io::Read;
+
+use crate::MarkerDetector;
+
+const CHUNK_LEN : usize = 64 * 1024;
+
+pub enum Event<'a> {
+  // Position just past a start-of-packet marker.
+  StartOfPacket(u64),
+  // Position just past a start-of-message marker; a new message begins here.
+  StartOfMessage(u64),
+  // Next bytes of the current message, which runs up to the start of the
+  // following start-of-message marker.
+  MessageData(&'a [u8]),
+}
+
+// Reads reader to the end in fixed-size chunks and reports every marker to
+// on_event. Memory use doesn't depend on the stream length.
+pub fn decode(reader : &mut impl Read, packet_len : usize, message_len : usize,
+    on_event : &mut impl FnMut(Event) -> io::Result<()>) -> io::Result<()> {
+  let mut packet = MarkerDetector::new(packet_len);
+  let mut message = MarkerDetector::new(message_len);
+  let mut chunk = vec![0u8; CHUNK_LEN];
+
+  // Message bytes not handed out yet. The last message_len of them may turn
+  // out to be the next marker, so they are held back until that's known.
+  let mut pending : Vec<u8> = Vec::with_capacity(CHUNK_LEN + message_len);
+  let mut in_message = false;
+
+  loop {
+    let len = match reader.read(&mut chunk) {
+      Ok(0) => break,
+      Ok(len) => len,
+      Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+      Err(err) => return Err(err),
+    };
+
+    for byte in &chunk[..len] {
+      if in_message {
+        pending.push(*byte);
+      }
+
+      if let Some(pos) = packet.push(*byte) {
+        on_event(Event::StartOfPacket(pos))?;
+      }
+
+      if let Some(pos) = message.push(*byte) {
+        if in_message {
+          let data_len = pending.len() - message_len;
+          if data_len > 0 {
+            on_event(Event::MessageData(&pending[..data_len]))?;
+          }
+          pending.clear();
+        }
+        on_event(Event::StartOfMessage(pos))?;
+        in_message = true;
+      }
+    }
+
+    if pending.len() > message_len {
+      let data_len = pending.len() - message_len;
+      on_event(Event::MessageData(&pending[..data_len]))?;
+      pending.drain(..data_len);
+    }
+  }
+
+  if !pending.is_empty() {
+    on_event(Event::MessageData(&pending))?;
+  }
+
+  Ok(())
+}
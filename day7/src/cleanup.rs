@@ -0,0 +1,147 @@
+use std::collections::HashMap;
+
+use crate::tree::{FileSystem, NodeId};
+
+// Sorted union of reachable and taken, two sorted lists of sums. Only the
+// smallest sum that frees enough is kept, since adding to a bigger one can't
+// do better. Calls added for every kept sum that wasn't in reachable.
+fn merge(reachable : &[usize], taken : impl Iterator<Item = usize>, min_space_to_free : usize,
+    mut added : impl FnMut(usize)) -> Vec<usize> {
+  let mut result = Vec::with_capacity(reachable.len());
+  let mut reachable = reachable.iter().copied().peekable();
+  let mut taken = taken.peekable();
+  loop {
+    let sum = match (reachable.peek(), taken.peek()) {
+      (None, None) => break,
+      (Some(old), Some(new)) if old == new => {
+        taken.next();
+        reachable.next().unwrap()
+      },
+      (Some(old), Some(new)) if old > new => {
+        added(*new);
+        taken.next().unwrap()
+      },
+      (Some(_), _) => reachable.next().unwrap(),
+      (None, Some(new)) => {
+        added(*new);
+        taken.next().unwrap()
+      },
+    };
+    result.push(sum);
+    if sum >= min_space_to_free {
+      break;
+    }
+  }
+  result
+}
+
+// Finds the non-nested set of nodes whose sizes add up to the least amount
+// that is still at least min_space_to_free. Directories are always
+// candidates, files only if include_files is set. Returns None if even
+// deleting everything isn't enough.
+//
+// Walks the candidates in preorder, where taking a node means skipping the
+// rest of its subtree, and tracks which totals are reachable from each
+// position as a sorted list. Memory goes with the number of distinct totals
+// below min_space_to_free, not with how many bytes they add up to.
+pub fn plan_deletion(fs : &FileSystem, min_space_to_free : usize, include_files : bool) -> Option<Vec<NodeId>> {
+  let candidates : Vec<NodeId> = fs.descendants(FileSystem::ROOT)
+    .filter(|id| include_files || fs.node(*id).is_dir())
+    .collect();
+
+  // Position just past each candidate's subtree.
+  let mut position : HashMap<NodeId, usize> = HashMap::new();
+  for (idx, id) in candidates.iter().enumerate() {
+    position.insert(*id, idx);
+  }
+  let mut subtree_end = vec![0; candidates.len()];
+  for (idx, id) in candidates.iter().enumerate().rev() {
+    subtree_end[idx] = fs.node(*id).children().iter()
+      .filter_map(|child| position.get(child).map(|child_idx| subtree_end[*child_idx]))
+      .max()
+      .unwrap_or(idx + 1);
+  }
+
+  // Reachable sets are only kept while some earlier candidate still needs
+  // them, which is at most one per level of the tree.
+  let mut pending_uses : HashMap<usize, usize> = HashMap::new();
+  for end in &subtree_end {
+    *pending_uses.entry(*end).or_insert(0) += 1;
+  }
+  let mut saved : HashMap<usize, Vec<usize>> = HashMap::new();
+
+  // Last (highest) position each sum is reachable from.
+  let mut last_reached : HashMap<usize, usize> = HashMap::new();
+
+  let mut reachable = vec![0];
+  if pending_uses.contains_key(&candidates.len()) {
+    saved.insert(candidates.len(), reachable.clone());
+  }
+
+  for idx in (0..candidates.len()).rev() {
+    let size = fs.node(candidates[idx]).size;
+    let end = subtree_end[idx];
+    let taken = saved[&end].iter().map(|sum| sum + size);
+    // A sum can drop out when a smaller one frees enough and come back
+    // later, but the first position it was reached from stays valid.
+    reachable = merge(&reachable, taken, min_space_to_free, |sum| {
+      last_reached.entry(sum).or_insert(idx);
+    });
+
+    let uses = pending_uses.get_mut(&end).unwrap();
+    *uses -= 1;
+    if *uses == 0 {
+      saved.remove(&end);
+    }
+    if pending_uses.get(&idx).is_some_and(|uses| *uses > 0) {
+      saved.insert(idx, reachable.clone());
+    }
+  }
+
+  let best = reachable.last().copied().filter(|sum| *sum >= min_space_to_free)?;
+
+  // Follow the positions back: a sum first reachable from idx must take
+  // candidate idx, and the rest of it is reachable from past its subtree.
+  let mut chosen = Vec::new();
+  let mut remaining = best;
+  while remaining > 0 {
+    let idx = last_reached[&remaining];
+    chosen.push(candidates[idx]);
+    remaining -= fs.node(candidates[idx]).size;
+  }
+
+  Some(chosen)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn parse(text : &str) -> FileSystem {
+    crate::parse_commands(text.lines().map(|line| Ok(line.to_string()))).unwrap()
+  }
+
+  fn freed(fs : &FileSystem, chosen : &[NodeId]) -> usize {
+    chosen.iter().map(|id| fs.node(*id).size).sum()
+  }
+
+  #[test]
+  fn terabyte_sizes() {
+    let fs = parse("$ cd /\n$ ls\ndir a\ndir b\n$ cd a\n$ ls\n4000000000000 x\n$ cd /b\n$ ls\n2500000000000 y\n");
+    let chosen = plan_deletion(&fs, 6000000000000, false).unwrap();
+    assert_eq!(freed(&fs, &chosen), 6500000000000);
+    assert_eq!(chosen.len(), 2);
+    assert_eq!(plan_deletion(&fs, 7000000000000, false), None);
+  }
+
+  #[test]
+  fn example() {
+    let fs = parse(include_str!("../assets/example.txt"));
+    // Same answer as part 2 with directories only; with files, c.dat alone
+    // comes closer.
+    let chosen = plan_deletion(&fs, 8381165, false).unwrap();
+    assert_eq!(freed(&fs, &chosen), 24933642);
+    let chosen = plan_deletion(&fs, 8381165, true).unwrap();
+    assert_eq!(freed(&fs, &chosen), 8504156);
+  }
+}
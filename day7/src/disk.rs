@@ -0,0 +1,156 @@
+use std::fs;
+use std::fs::File;
+use std::io;
+use std::path::{Component, Path};
+
+use crate::tree::{FileSystem, NodeId};
+
+// Recreates the tree under target, with every file a sparse file of its
+// recorded size so that huge transcripts take next to no disk space.
+pub fn materialize(fs : &FileSystem, target : &Path) -> io::Result<()> {
+  fs::create_dir_all(target)?;
+  for id in fs.descendants(FileSystem::ROOT).skip(1) {
+    let node = fs.node(id);
+    // Paths are absolute within the tree, so make them relative to target.
+    let tree_path = fs.path(id);
+    let relative = Path::new(&tree_path[1..]);
+    if !relative.components().all(|component| matches!(component, Component::Normal(_))) {
+      return Err(io::Error::new(io::ErrorKind::InvalidInput,
+        format!("{} would end up outside {}", tree_path, target.display())));
+    }
+    let path = target.join(relative);
+    if node.is_dir() {
+      fs::create_dir_all(&path)?;
+    } else {
+      File::create(&path)?.set_len(node.size as u64)?;
+    }
+  }
+  Ok(())
+}
+
+// Builds the tree for a real directory, using each file's length. Symlinks
+// and other special files are skipped.
+pub fn scan(root : &Path) -> io::Result<FileSystem> {
+  let mut fs = FileSystem::new();
+  scan_dir(&mut fs, FileSystem::ROOT, root)?;
+  Ok(fs)
+}
+
+fn scan_dir(fs : &mut FileSystem, dir : NodeId, path : &Path) -> io::Result<()> {
+  let mut entries = fs::read_dir(path)?.collect::<io::Result<Vec<_>>>()?;
+  entries.sort_by_key(|entry| entry.file_name());
+
+  for entry in entries {
+    let name = entry.file_name().into_string()
+      .map_err(|name| io::Error::new(io::ErrorKind::InvalidData, format!("{:?} isn't valid UTF-8", name)))?;
+    if name.contains(char::is_whitespace) {
+      return Err(io::Error::new(io::ErrorKind::InvalidData,
+        format!("{:?} contains whitespace, which a transcript can't represent", entry.path())));
+    }
+
+    let file_type = entry.file_type()?;
+    if file_type.is_dir() {
+      let id = fs.add_dir(dir, &name).map_err(io::Error::other)?;
+      scan_dir(fs, id, &entry.path())?;
+    } else if file_type.is_file() {
+      fs.add_file(dir, &name, entry.metadata()?.len() as usize).map_err(io::Error::other)?;
+    }
+  }
+  Ok(())
+}
+
+// A cd/ls transcript that parse_commands turns back into the same tree.
+pub fn transcript(fs : &FileSystem) -> String {
+  let mut result = String::from("$ cd /\n");
+  transcript_dir(fs, FileSystem::ROOT, &mut result);
+  result
+}
+
+fn transcript_dir(fs : &FileSystem, dir : NodeId, result : &mut String) {
+  result.push_str("$ ls\n");
+  let children = fs.node(dir).children();
+  for child in children {
+    let node = fs.node(*child);
+    if node.is_dir() {
+      result.push_str(&format!("dir {}\n", node.name));
+    } else {
+      result.push_str(&format!("{} {}\n", node.size, node.name));
+    }
+  }
+
+  for child in children {
+    let node = fs.node(*child);
+    if node.is_dir() {
+      result.push_str(&format!("$ cd {}\n", node.name));
+      transcript_dir(fs, *child, result);
+      result.push_str("$ cd ..\n");
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use std::collections::BTreeMap;
+  use std::env;
+  use std::process;
+
+  use super::*;
+  use crate::parse_commands;
+
+  fn parse(transcript : &str) -> FileSystem {
+    parse_commands(transcript.lines().map(|line| Ok(line.to_string()))).unwrap()
+  }
+
+  // Every node by path, with whether it's a directory and its size. For a
+  // directory that's the total of everything below it, like
+  // du --apparent-size -b. Plain du counts allocated blocks, which for
+  // these sparse files is next to nothing.
+  fn sizes(fs : &FileSystem) -> BTreeMap<String, (bool, usize)> {
+    fs.descendants(FileSystem::ROOT)
+      .map(|id| (fs.path(id), (fs.node(id).is_dir(), fs.node(id).size)))
+      .collect()
+  }
+
+  // transcript -> materialize -> scan -> transcript, checking the tree and
+  // the directory sizes survive every step.
+  fn assert_round_trips(name : &str, transcript_text : &str) {
+    let fs = parse(transcript_text);
+    let target = env::temp_dir().join(format!("day7-{}-{}", name, process::id()));
+    let _ = fs::remove_dir_all(&target);
+
+    materialize(&fs, &target).unwrap();
+    let scanned = scan(&target);
+    fs::remove_dir_all(&target).unwrap();
+    let scanned = scanned.unwrap();
+
+    assert_eq!(sizes(&scanned), sizes(&fs));
+    assert_eq!(sizes(&parse(&transcript(&scanned))), sizes(&fs));
+  }
+
+  #[test]
+  fn example_round_trips() {
+    assert_round_trips("example", include_str!("../assets/example.txt"));
+  }
+
+  #[test]
+  fn input_round_trips() {
+    assert_round_trips("input", include_str!("../assets/input.txt"));
+  }
+
+  #[test]
+  fn names_that_leave_their_directory() {
+    for name in ["..", ".", "../escaped.txt", "a/b"] {
+      let text = format!("$ cd /\n$ ls\n10 {}\n", name);
+      let err = parse_commands(text.lines().map(|line| Ok(line.to_string()))).err().unwrap();
+      assert!(err.to_string().starts_with("line 3: "), "{}", err);
+    }
+
+    // A tree built some other way still can't write outside the target.
+    let mut fs = FileSystem::new();
+    fs.add_file(FileSystem::ROOT, "..", 10).unwrap();
+    let target = env::temp_dir().join(format!("day7-escape-{}", process::id())).join("target");
+    let result = materialize(&fs, &target);
+    fs::remove_dir_all(target.parent().unwrap()).unwrap();
+    assert_eq!(result.unwrap_err().kind(), io::ErrorKind::InvalidInput);
+  }
+}
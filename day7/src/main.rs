@@ -1,119 +1,262 @@
-use std::cmp;
 use std::collections::HashMap;
+use std::env;
+use std::error::Error;
 use std::io;
+use std::path::Path;
 
-fn update_dir_sizes(cwd : &mut Vec<String>, dirs : &mut HashMap<String, usize>, additional_size : usize) {
-  for i in 0..cwd.len() {
-    let dir = cwd[0..=i].join("/");
-    *dirs.entry(dir).or_insert(0usize) += additional_size;
+mod cleanup;
+mod disk;
+mod tree;
+mod views;
+
+use tree::{FileSystem, NodeId};
+
+// Follows an absolute or relative cd path from cwd, creating directories
+// that haven't been listed yet.
+fn resolve_path(fs : &mut FileSystem, cwd : NodeId, path : &str) -> Result<NodeId, String> {
+  let mut dir = if path.starts_with('/') { FileSystem::ROOT } else { cwd };
+  for name in path.split('/') {
+    match name {
+      "" | "." => (),
+      // Like a shell, going up from the root stays at the root.
+      ".." => dir = fs.node(dir).parent.unwrap_or(FileSystem::ROOT),
+      _ => dir = fs.add_dir(dir, name)?,
+    }
+  }
+  Ok(dir)
+}
+
+// A listed name has to be a single path component that can't leave its
+// directory.
+fn check_name(name : &str) -> Result<(), String> {
+  if name == "." || name == ".." || name.contains('/') {
+    return Err(format!("{:?} isn't a valid name", name));
   }
+  Ok(())
 }
 
-fn parse_commands(lines : impl Iterator<Item = io::Result<String>>) -> HashMap<String, usize> {
-  let mut dirs = HashMap::new();
-  let mut cwd = Vec::new();
+// Rebuilds the filesystem from a cd/ls transcript. Listing a directory more
+// than once is fine as long as the listings agree.
+fn parse_commands(lines : impl Iterator<Item = io::Result<String>>) -> Result<FileSystem, Box<dyn Error>> {
+  let mut fs = FileSystem::new();
+  let mut cwd : NodeId = FileSystem::ROOT;
   let mut is_listing = false;
-  let mut total_dir_size = 0;
+  // Line each node was first listed on, for reporting conflicts.
+  let mut listed_on : HashMap<NodeId, usize> = HashMap::new();
 
-  for line in lines {
-    let line = line.unwrap();
+  for (idx, line) in lines.enumerate() {
+    let line_num = idx + 1;
+    let line = line?;
     let parts : Vec<&str> = line.split_whitespace().collect();
-    match parts[0] {
-      "$" => { // Execute a command.
-        if is_listing {
-          update_dir_sizes(&mut cwd, &mut dirs, total_dir_size);
-          total_dir_size = 0;
-          is_listing = false;
-        }
-        match parts[1] {
-          "cd" => {
-            assert_eq!(parts.len(), 3);
-            match parts[2] {
-              ".." => {
-                cwd.pop();
-              },
-              "." => {
-                panic!("unexpected path token: {}", parts[2]);
-              }
-              _ => {
-                cwd.push(parts[2].to_string());
-              }
-            }
-          }
-          "ls" => {
-            assert_eq!(parts.len(), 2);
-            is_listing = true;
-          }
-          _ => {
-            panic!("unexpected command: {}", parts[1]);
-          }
+    let err = |msg : String| format!("line {}: {}", line_num, msg);
 
+    match parts.as_slice() {
+      [] => {
+        continue;
+      },
+      ["$", "cd", path] => {
+        is_listing = false;
+        cwd = resolve_path(&mut fs, cwd, path).map_err(err)?;
+      },
+      ["$", "ls"] => {
+        is_listing = true;
+      },
+      ["$", ..] => {
+        return Err(err(format!("unexpected command: {}", line)).into());
+      },
+      [kind, name] => {
+        if !is_listing {
+          return Err(err(String::from("listing output without a preceding ls")).into());
         }
-      }
-      _ => {
-        assert!(is_listing);
-        assert_eq!(parts.len(), 2);
-        match parts[0] {
-          "dir" => {
-            // Ignore directories that show up on the list.
-          },
+        check_name(name).map_err(err)?;
+        let id = match *kind {
+          "dir" => fs.add_dir(cwd, name),
           _ => {
             // Found a file with a size.
-            total_dir_size += parts[0].parse::<usize>().unwrap();
-          }
-        }
-      }
+            let size = kind.parse::<usize>().map_err(|_| err(format!("bad file size: {}", kind)))?;
+            fs.add_file(cwd, name, size)
+          },
+        };
+        let id = id.map_err(|msg| match listed_on.get(&fs.child(cwd, name).unwrap()) {
+          Some(first_line) => err(format!("{} (line {})", msg, first_line)),
+          None => err(msg),
+        })?;
+        listed_on.entry(id).or_insert(line_num);
+      },
+      _ => {
+        return Err(err(format!("unexpected line: {}", line)).into());
+      },
     }
   }
 
-  // Update with any sizes from the final command.
-  update_dir_sizes(&mut cwd, &mut dirs, total_dir_size);
+  Ok(fs)
+}
 
-  dirs
+const TOTAL_DISK_SPACE : usize = 70000000usize;
+const REQUIRED_DISK_SPACE : usize = 30000000usize;
+
+// How much has to be deleted so that required bytes of a disk_size disk are
+// unused.
+fn min_space_to_free(fs : &FileSystem, disk_size : usize, required : usize) -> Result<usize, String> {
+  let used = fs.node(FileSystem::ROOT).size;
+  let unused_disk_space = disk_size.checked_sub(used)
+    .ok_or(format!("{} bytes in use don't fit on a {} byte disk", used, disk_size))?;
+  Ok(required.saturating_sub(unused_disk_space))
 }
 
-fn find_target_dir_size(dirs : &HashMap<String, usize>) -> usize {
-  const TOTAL_DISK_SPACE : usize = 70000000usize;
-  const REQUIRED_DISK_SPACE : usize = 30000000usize;
-  let unused_disk_space = TOTAL_DISK_SPACE - *dirs.get("/").unwrap();
-  assert!(unused_disk_space < REQUIRED_DISK_SPACE);
-
-  let min_space_to_free = REQUIRED_DISK_SPACE - unused_disk_space;
-  let mut candidate_dir_size = std::usize::MAX;
-  for entry in dirs {
-    let dir_size = *(entry.1);
-    if dir_size >= min_space_to_free {
-      #[cfg(debug_assertions)]
-      println!("candidate dir {:?}", entry);
-
-      candidate_dir_size = cmp::min(candidate_dir_size, dir_size);
-    }
-  }
+fn find_target_dir_size(fs : &FileSystem, min_space_to_free : usize) -> Option<usize> {
+  let candidate = fs.dirs()
+    .filter(|id| fs.node(*id).size >= min_space_to_free)
+    .min_by_key(|id| fs.node(*id).size)?;
+
+  #[cfg(debug_assertions)]
+  println!("candidate dir {} ({})", fs.path(candidate), fs.node(candidate).size);
 
-  candidate_dir_size
+  Some(fs.node(candidate).size)
 }
 
-fn sum_small_dirs(dirs : &HashMap<String, usize>) -> usize {
+fn print_deletion_plan(fs : &FileSystem, min_space_to_free : usize, include_files : bool) {
+  println!("need to free: {}", min_space_to_free);
+  let Some(chosen) = cleanup::plan_deletion(fs, min_space_to_free, include_files) else {
+    println!("not possible, everything together is only {}", fs.node(FileSystem::ROOT).size);
+    return;
+  };
+
+  for id in &chosen {
+    let node = fs.node(*id);
+    let kind = if node.is_dir() { "dir" } else { "file" };
+    println!("  delete {} ({}): frees {}", fs.path(*id), kind, node.size);
+  }
+  let total : usize = chosen.iter().map(|id| fs.node(*id).size).sum();
+  println!("total freed: {} ({} more than needed)", total, total - min_space_to_free);
+}
+
+fn sum_small_dirs(fs : &FileSystem) -> usize {
   const MAX_DIR_SIZE : usize = 100000;
   let mut dir_size_sum = 0usize;
-  for entry in dirs {
+  for id in fs.dirs() {
+    let size = fs.node(id).size;
+
     #[cfg(debug_assertions)]
-    println!("directory: {:?}", entry);
+    println!("directory: {} ({})", fs.path(id), size);
 
-    if *(entry.1) <= MAX_DIR_SIZE {
-      dir_size_sum += entry.1;
+    if size <= MAX_DIR_SIZE {
+      dir_size_sum += size;
     }
   }
 
   dir_size_sum
 }
 
-fn main() {
-  let dirs = parse_commands(io::stdin().lines());
+enum View {
+  Tree(views::SortOrder),
+  Du(usize),
+  Find(views::Filter),
+  // Whether files may be deleted too, not just directories.
+  DeletionPlan(bool),
+  Transcript,
+  Materialize(String),
+}
+
+struct Options {
+  view : Option<View>,
+  // Real directory to read instead of a transcript on stdin.
+  from_dir : Option<String>,
+  disk_size : usize,
+  required : usize,
+}
+
+fn parse_args() -> Result<Options, Box<dyn Error>> {
+  let mut view = None;
+  let mut disk_size = TOTAL_DISK_SPACE;
+  let mut required = REQUIRED_DISK_SPACE;
+  let mut include_files = false;
+  let mut order = views::SortOrder::Name;
+  let mut filter = views::Filter::default();
+  let mut finding = false;
+  let mut from_dir = None;
+
+  let mut args = env::args().skip(1);
+  while let Some(arg) = args.next() {
+    let mut value = || args.next().ok_or(format!("{} requires a value", arg));
+    match arg.as_str() {
+      "--tree" => view = Some(View::Tree(order)),
+      "--sort" => {
+        order = match value()?.as_str() {
+          "name" => views::SortOrder::Name,
+          "size" => views::SortOrder::Size,
+          other => return Err(format!("--sort expects name or size, got {}", other).into()),
+        };
+        view = Some(View::Tree(order));
+      },
+      "--du" => view = Some(View::Du(value()?.parse::<usize>()?)),
+      "--disk-size" => disk_size = value()?.parse::<usize>()?,
+      "--required" => required = value()?.parse::<usize>()?,
+      "--plan-deletion" => view = Some(View::DeletionPlan(include_files)),
+      "--include-files" => {
+        include_files = true;
+        view = Some(View::DeletionPlan(include_files));
+      },
+      "--transcript" => view = Some(View::Transcript),
+      "--materialize" => view = Some(View::Materialize(value()?)),
+      "--from-dir" => from_dir = Some(value()?),
+      "--find" => finding = true,
+      "--min-size" => {
+        filter.min_size = Some(value()?.parse::<usize>()?);
+        finding = true;
+      },
+      "--max-size" => {
+        filter.max_size = Some(value()?.parse::<usize>()?);
+        finding = true;
+      },
+      "--name" => {
+        filter.name = Some(value()?);
+        finding = true;
+      },
+      _ => return Err(format!("unexpected argument: {}", arg).into()),
+    }
+  }
+
+  if finding {
+    if view.is_some() {
+      return Err("--find can't be combined with other views".into());
+    }
+    view = Some(View::Find(filter));
+  }
+  Ok(Options { view, from_dir, disk_size, required })
+}
+
+fn main() -> Result<(), Box<dyn Error>> {
+  let options = parse_args()?;
+  let fs = match &options.from_dir {
+    Some(dir) => disk::scan(Path::new(dir))?,
+    None => parse_commands(io::stdin().lines())?,
+  };
+  let min_space_to_free = || min_space_to_free(&fs, options.disk_size, options.required);
+
+  if let Some(view) = options.view {
+    match view {
+      View::Tree(order) => print!("{}", views::render_tree(&fs, order)),
+      View::Du(max_depth) => print!("{}", views::render_du(&fs, max_depth)),
+      View::Find(filter) => {
+        for path in views::find(&fs, &filter) {
+          println!("{}", path);
+        }
+      },
+      View::DeletionPlan(include_files) => print_deletion_plan(&fs, min_space_to_free()?, include_files),
+      View::Transcript => print!("{}", disk::transcript(&fs)),
+      View::Materialize(target) => disk::materialize(&fs, Path::new(&target))?,
+    }
+    return Ok(());
+  }
 
-  let result = sum_small_dirs(&dirs);
+  let result = sum_small_dirs(&fs);
   println!("part 1: {}", result);
 
-  let result = find_target_dir_size(&dirs);
-  println!("part 2: {}", result);
+  match find_target_dir_size(&fs, min_space_to_free()?) {
+    Some(result) => println!("part 2: {}", result),
+    None => println!("part 2: no directory is big enough"),
+  }
+
+  Ok(())
 }
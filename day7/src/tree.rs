@@ -0,0 +1,139 @@
+// Index of a node in a FileSystem.
+pub type NodeId = usize;
+
+#[derive(Debug)]
+pub enum NodeKind {
+  Dir { children : Vec<NodeId> },
+  File,
+}
+
+#[derive(Debug)]
+pub struct Node {
+  pub name : String,
+  pub parent : Option<NodeId>,
+  pub kind : NodeKind,
+  // File size, or for a directory the total size of everything below it.
+  // Kept up to date as files are added.
+  pub size : usize,
+}
+
+impl Node {
+  pub fn is_dir(&self) -> bool {
+    matches!(self.kind, NodeKind::Dir { .. })
+  }
+
+  pub fn children(&self) -> &[NodeId] {
+    match &self.kind {
+      NodeKind::Dir { children } => children,
+      NodeKind::File => &[],
+    }
+  }
+}
+
+// Directory tree with every node stored in one vector and linked by index.
+#[derive(Debug)]
+pub struct FileSystem {
+  nodes : Vec<Node>,
+}
+
+impl FileSystem {
+  pub const ROOT : NodeId = 0;
+
+  pub fn new() -> FileSystem {
+    let root = Node {
+      name : String::from("/"),
+      parent : None,
+      kind : NodeKind::Dir { children : Vec::new() },
+      size : 0,
+    };
+    FileSystem { nodes : vec![root] }
+  }
+
+  pub fn node(&self, id : NodeId) -> &Node {
+    &self.nodes[id]
+  }
+
+  pub fn child(&self, dir : NodeId, name : &str) -> Option<NodeId> {
+    self.nodes[dir].children().iter().copied().find(|id| self.nodes[*id].name == name)
+  }
+
+  fn add_node(&mut self, dir : NodeId, name : &str, kind : NodeKind, size : usize) -> NodeId {
+    let id = self.nodes.len();
+    self.nodes.push(Node { name : name.to_string(), parent : Some(dir), kind, size : 0 });
+    match &mut self.nodes[dir].kind {
+      NodeKind::Dir { children } => children.push(id),
+      NodeKind::File => panic!("{} is not a directory", self.path(dir)),
+    }
+    self.grow(id, size);
+    id
+  }
+
+  // Adds size to a node and every directory above it.
+  fn grow(&mut self, id : NodeId, size : usize) {
+    let mut node = Some(id);
+    while let Some(id) = node {
+      self.nodes[id].size += size;
+      node = self.nodes[id].parent;
+    }
+  }
+
+  // Returns the named subdirectory of dir, creating it if needed. Fails if
+  // there is a file by that name.
+  pub fn add_dir(&mut self, dir : NodeId, name : &str) -> Result<NodeId, String> {
+    match self.child(dir, name) {
+      Some(id) if self.nodes[id].is_dir() => Ok(id),
+      Some(id) => Err(format!("{} is a file, not a directory", self.path(id))),
+      None => Ok(self.add_node(dir, name, NodeKind::Dir { children : Vec::new() }, 0)),
+    }
+  }
+
+  // Returns the named file in dir, creating it with size if needed. Fails if
+  // there is a directory by that name or the file has a different size.
+  pub fn add_file(&mut self, dir : NodeId, name : &str, size : usize) -> Result<NodeId, String> {
+    match self.child(dir, name) {
+      Some(id) if self.nodes[id].is_dir() => Err(format!("{} is a directory, not a file", self.path(id))),
+      Some(id) if self.nodes[id].size != size =>
+        Err(format!("{} has size {}, but was listed before with size {}", self.path(id), size, self.nodes[id].size)),
+      Some(id) => Ok(id),
+      None => Ok(self.add_node(dir, name, NodeKind::File, size)),
+    }
+  }
+
+  // Absolute path, like "/a/e".
+  pub fn path(&self, id : NodeId) -> String {
+    let mut names : Vec<&str> = self.ancestors(id).map(|id| self.nodes[id].name.as_str()).collect();
+    names.pop(); // The root is implied by the leading slash.
+    names.reverse();
+    format!("/{}", names.join("/"))
+  }
+
+  // The node itself, then its parent and so on up to the root.
+  pub fn ancestors(&self, id : NodeId) -> impl Iterator<Item = NodeId> + '_ {
+    std::iter::successors(Some(id), |id| self.nodes[*id].parent)
+  }
+
+  // Every node below id, including id itself, parents before children.
+  pub fn descendants(&self, id : NodeId) -> Descendants<'_> {
+    Descendants { fs : self, stack : vec![id] }
+  }
+
+  pub fn dirs(&self) -> impl Iterator<Item = NodeId> + '_ {
+    self.descendants(FileSystem::ROOT).filter(|id| self.nodes[*id].is_dir())
+  }
+}
+
+pub struct Descendants<'a> {
+  fs : &'a FileSystem,
+  stack : Vec<NodeId>,
+}
+
+impl Iterator for Descendants<'_> {
+  type Item = NodeId;
+
+  fn next(&mut self) -> Option<NodeId> {
+    let id = self.stack.pop()?;
+    // Push in reverse so children come out in listing order.
+    self.stack.extend(self.fs.nodes[id].children().iter().rev());
+    Some(id)
+  }
+}
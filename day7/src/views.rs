@@ -0,0 +1,106 @@
+use std::cmp::Reverse;
+
+use crate::tree::{FileSystem, NodeId};
+
+#[derive(Clone, Copy)]
+pub enum SortOrder {
+  Name,
+  // Largest first.
+  Size,
+}
+
+// Listing in the same style as the puzzle description:
+//   - / (dir, size=48381165)
+//     - a (dir, size=94853)
+pub fn render_tree(fs : &FileSystem, order : SortOrder) -> String {
+  let mut result = String::new();
+  render_node(fs, FileSystem::ROOT, 0, order, &mut result);
+  result
+}
+
+fn render_node(fs : &FileSystem, id : NodeId, depth : usize, order : SortOrder, result : &mut String) {
+  let node = fs.node(id);
+  let kind = if node.is_dir() { "dir" } else { "file" };
+  result.push_str(&format!("{}- {} ({}, size={})\n", "  ".repeat(depth), node.name, kind, node.size));
+
+  let mut children = node.children().to_vec();
+  match order {
+    SortOrder::Name => children.sort_by(|a, b| fs.node(*a).name.cmp(&fs.node(*b).name)),
+    SortOrder::Size => children.sort_by_key(|id| (Reverse(fs.node(*id).size), fs.node(*id).name.clone())),
+  }
+  for child in children {
+    render_node(fs, child, depth + 1, order, result);
+  }
+}
+
+// Like `du -d max_depth`: one line per directory no deeper than max_depth,
+// subdirectories before the directory that holds them.
+pub fn render_du(fs : &FileSystem, max_depth : usize) -> String {
+  let mut result = String::new();
+  render_du_dir(fs, FileSystem::ROOT, 0, max_depth, &mut result);
+  result
+}
+
+fn render_du_dir(fs : &FileSystem, id : NodeId, depth : usize, max_depth : usize, result : &mut String) {
+  if depth < max_depth {
+    for child in fs.node(id).children() {
+      if fs.node(*child).is_dir() {
+        render_du_dir(fs, *child, depth + 1, max_depth, result);
+      }
+    }
+  }
+  result.push_str(&format!("{}\t{}\n", fs.node(id).size, fs.path(id)));
+}
+
+#[derive(Default)]
+pub struct Filter {
+  pub min_size : Option<usize>,
+  pub max_size : Option<usize>,
+  // Shell-style pattern for the node name, with * and ?.
+  pub name : Option<String>,
+}
+
+impl Filter {
+  fn matches(&self, fs : &FileSystem, id : NodeId) -> bool {
+    let node = fs.node(id);
+    self.min_size.is_none_or(|min| node.size >= min) &&
+    self.max_size.is_none_or(|max| node.size <= max) &&
+    self.name.as_ref().is_none_or(|pattern| glob_match(pattern, &node.name))
+  }
+}
+
+// Like `find`: paths of every file and directory the filter accepts.
+pub fn find(fs : &FileSystem, filter : &Filter) -> Vec<String> {
+  fs.descendants(FileSystem::ROOT)
+    .filter(|id| filter.matches(fs, *id))
+    .map(|id| fs.path(id))
+    .collect()
+}
+
+// Matches * against any run of chars and ? against exactly one.
+fn glob_match(pattern : &str, name : &str) -> bool {
+  let pattern : Vec<char> = pattern.chars().collect();
+  let name : Vec<char> = name.chars().collect();
+  let (mut p, mut n) = (0, 0);
+  // Where to resume after the last *: (pattern index, name index).
+  let mut backtrack : Option<(usize, usize)> = None;
+
+  while n < name.len() {
+    if p < pattern.len() && (pattern[p] == '?' || pattern[p] == name[n]) {
+      p += 1;
+      n += 1;
+    } else if p < pattern.len() && pattern[p] == '*' {
+      backtrack = Some((p + 1, n));
+      p += 1;
+    } else if let Some((star_p, star_n)) = backtrack {
+      // Let the last * swallow one more char.
+      p = star_p;
+      n = star_n + 1;
+      backtrack = Some((star_p, star_n + 1));
+    } else {
+      return false;
+    }
+  }
+
+  pattern[p..].iter().all(|ch| *ch == '*')
+}
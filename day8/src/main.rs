@@ -1,154 +1,525 @@
 use std::cmp;
+use std::env;
+use std::error::Error;
+use std::fmt;
+use std::fs::File;
 use std::io;
+use std::io::Write;
+use std::str::FromStr;
+use std::time::Instant;
 
-fn parse_grid(lines : impl Iterator<Item = io::Result<String>>) -> Vec<Vec<u8>> {
-  let mut grid : Vec<Vec<u8>> = Vec::new();
+enum InputFormat {
+  // One digit per tree, like the puzzle input.
+  Digits,
+  // Heights of any size separated by whitespace or commas.
+  Separated,
+}
+
+// Parses one height per char of a Digits line.
+fn parse_digits<H : From<u8>>(line : &str, line_num : usize) -> Result<Vec<H>, String> {
+  line.chars().enumerate().map(|(col, ch)| match ch.to_digit(10) {
+    Some(digit) => Ok(H::from(digit as u8)),
+    None => Err(format!("line {}, column {}: unexpected character {:?}", line_num, col + 1, ch)),
+  }).collect()
+}
+
+fn parse_separated<H : FromStr>(line : &str, line_num : usize) -> Result<Vec<H>, String> {
+  line.split(|ch : char| ch == ',' || ch.is_whitespace())
+    .filter(|token| !token.is_empty())
+    .enumerate()
+    .map(|(col, token)| token.parse::<H>()
+      .map_err(|_| format!("line {}, column {}: {:?} isn't a valid height", line_num, col + 1, token)))
+    .collect()
+}
+
+fn parse_grid<H : From<u8> + FromStr>(lines : impl Iterator<Item = io::Result<String>>, format : &InputFormat)
+    -> Result<Vec<Vec<H>>, Box<dyn Error>> {
+  let mut grid : Vec<Vec<H>> = Vec::new();
 
-  for line in lines {
-    let chars : Vec<char> = line.unwrap().chars().collect();
-    let mut row : Vec<u8> = Vec::with_capacity(chars.len());
-    for ch in chars {
-      let height = ch as u8 - '0' as u8;
-      assert!(height <= 9);
-      row.push(height);
+  for (idx, line) in lines.enumerate() {
+    let line = line?;
+    if line.trim().is_empty() {
+      continue;
     }
-    grid.push(row);
 
-    #[cfg(debug_assertions)]
-    {
-      // Verify same number of columns in each row.
-      let len = grid.len();
-      if len > 1 {
-        assert_eq!(grid[len - 1].len(), grid[len - 2].len());
+    let row = match format {
+      InputFormat::Digits => parse_digits(line.trim_end(), idx + 1)?,
+      InputFormat::Separated => parse_separated(&line, idx + 1)?,
+    };
+
+    // Every row needs the same number of columns.
+    if let Some(first) = grid.first() {
+      if row.len() != first.len() {
+        return Err(format!("line {}: expected {} trees, got {}", idx + 1, first.len(), row.len()).into());
       }
     }
+    grid.push(row);
+  }
 
+  if grid.is_empty() {
+    return Err("no trees in the input".into());
   }
+  Ok(grid)
+}
+
+// Directions a tree can look in, in the order Views stores them, with the
+// (row, col) step each one takes.
+const DIRECTIONS : [(&str, isize, isize); 8] = [
+  ("left", 0, -1),
+  ("right", 0, 1),
+  ("up", -1, 0),
+  ("down", 1, 0),
+  ("up_left", -1, -1),
+  ("down_right", 1, 1),
+  ("up_right", -1, 1),
+  ("down_left", 1, -1),
+];
+const LEFT : usize = 0;
+const RIGHT : usize = 1;
+const UP : usize = 2;
+const DOWN : usize = 3;
+const UP_LEFT : usize = 4;
+const DOWN_RIGHT : usize = 5;
+const UP_RIGHT : usize = 6;
+const DOWN_LEFT : usize = 7;
 
-  grid
+#[derive(Debug, Clone, Copy)]
+enum DirectionSet {
+  Cardinal,
+  Diagonal,
+  All,
 }
 
-fn trees_visible_from_perimiter(grid : &Vec<Vec<u8>>) -> usize {
-  let mut visible = 0usize;
+impl DirectionSet {
+  fn directions(&self) -> &'static [usize] {
+    match self {
+      DirectionSet::Cardinal => &[LEFT, RIGHT, UP, DOWN],
+      DirectionSet::Diagonal => &[UP_LEFT, DOWN_RIGHT, UP_RIGHT, DOWN_LEFT],
+      DirectionSet::All => &[LEFT, RIGHT, UP, DOWN, UP_LEFT, DOWN_RIGHT, UP_RIGHT, DOWN_LEFT],
+    }
+  }
+}
 
-  let rows = grid.len();
-  let cols = grid[0].len();
+impl FromStr for DirectionSet {
+  type Err = String;
 
-  for row in 0..rows {
-    assert_eq!(cols, grid[row].len());
-    for col in 0..cols {
+  fn from_str(s : &str) -> Result<Self, Self::Err> {
+    match s {
+      "cardinal" => Ok(DirectionSet::Cardinal),
+      "diagonal" => Ok(DirectionSet::Diagonal),
+      "all" => Ok(DirectionSet::All),
+      _ => Err(format!("expected cardinal, diagonal or all, got {}", s)),
+    }
+  }
+}
 
-      let mut is_visible_left = true;
-      for x in 0..col {
-        if grid[row][x] >= grid[row][col] {
-          is_visible_left = false;
-          break;
-        }
-      }
+// Set on a distance when the tree can see all the way to the edge.
+const EDGE : u32 = 1 << 31;
 
-      let mut is_visible_right = true;
-      for x in col+1..cols {
-        if grid[row][x] >= grid[row][col] {
-          is_visible_right = false;
-          break;
-        }
-      }
+// A product of up to eight distances outgrows usize on grids of a few
+// hundred trees a side. Past u128 it saturates, which takes a grid far too
+// big to hold in memory.
+type Score = u128;
 
-      let mut is_visible_top = true;
-      for y in 0..row {
-        if grid[y][col] >= grid[row][col] {
-          is_visible_top = false;
-          break;
-        }
-      }
+// What every tree sees in each analyzed direction, row-major. A distance is
+// the number of trees visible, until a tree at least as tall or the edge,
+// with EDGE set if it's the edge. Directions outside the set are empty.
+struct Views {
+  rows : usize,
+  cols : usize,
+  set : DirectionSet,
+  distances : [Vec<u32>; 8],
+}
 
-      let mut is_visible_bottom = true;
-      for y in row+1..rows {
-        if grid[y][col] >= grid[row][col] {
-          is_visible_bottom = false;
-        }
-      }
+impl Views {
+  fn len(&self) -> usize {
+    self.rows * self.cols
+  }
 
-      let is_visible = is_visible_left || is_visible_right ||
-          is_visible_top || is_visible_bottom;
-      if is_visible {
-        visible += 1;
-      }
+  fn distance(&self, idx : usize, direction : usize) -> u32 {
+    self.distances[direction][idx] & !EDGE
+  }
 
-      #[cfg(debug_assertions)]
-      print!("{},", if is_visible { '1' } else { '0' });
-    }
+  // Whether the tree can be seen from outside the grid in this direction.
+  fn visible_from(&self, idx : usize, direction : usize) -> bool {
+    self.distances[direction][idx] & EDGE != 0
+  }
+
+  fn is_visible(&self, idx : usize) -> bool {
+    self.set.directions().iter().any(|direction| self.visible_from(idx, *direction))
+  }
 
-    #[cfg(debug_assertions)]
-    println!("");
+  fn scenic_score(&self, idx : usize) -> Score {
+    self.set.directions().iter()
+      .fold(1, |score : Score, direction| score.saturating_mul(self.distance(idx, *direction) as Score))
   }
+}
 
-  visible
+// How many steps from (row, col) to the edge going in a direction.
+fn steps_to_edge(rows : usize, cols : usize, row : usize, col : usize, (d_row, d_col) : (isize, isize)) -> u32 {
+  let rows_to_edge = match d_row { 1 => rows - 1 - row, -1 => row, _ => usize::MAX };
+  let cols_to_edge = match d_col { 1 => cols - 1 - col, -1 => col, _ => usize::MAX };
+  cmp::min(rows_to_edge, cols_to_edge) as u32
 }
 
-fn scenic_score(grid : &Vec<Vec<u8>>) -> usize {
-  let mut max_scenic_score = 0usize;
+// Works out how far every tree sees in a direction and in the opposite one.
+// Trees are visited so that the next tree in the first direction is always
+// done first, and a tree looks past a shorter one by jumping straight to
+// whatever blocked it. The trees jumped over are the ones a monotonic stack
+// would pop, and this tree is the first one they see in the opposite
+// direction, so the whole grid takes O(rows * cols) time. Reads and writes
+// go row by row.
+fn look<H : Ord + Copy>(heights : &[H], rows : usize, cols : usize, (d_row, d_col) : (isize, isize))
+    -> (Vec<u32>, Vec<u32>) {
+  const UNSEEN : u32 = u32::MAX;
+  let mut back = vec![0u32; rows * cols];
+  let mut ahead = vec![UNSEEN; rows * cols];
+  let offset = d_row * cols as isize + d_col;
+
+  for i in 0..rows {
+    let row = if d_row > 0 { rows - 1 - i } else { i };
+    for j in 0..cols {
+      let col = if d_col > 0 { cols - 1 - j } else { j };
+      let to_edge = steps_to_edge(rows, cols, row, col, (d_row, d_col));
+
+      let idx = row * cols + col;
+      let height = heights[idx];
+      let mut dist = 1;
+      back[idx] = loop {
+        if dist > to_edge {
+          break to_edge | EDGE;
+        }
+        let other = (idx as isize + offset * dist as isize) as usize;
+        let other_height = heights[other];
+        if other_height >= height {
+          if other_height == height {
+            ahead[other] = cmp::min(ahead[other], dist);
+          }
+          break dist;
+        }
+        // Nothing between here and other is as tall as other, and
+        // everything up to what blocks other is shorter than other.
+        ahead[other] = cmp::min(ahead[other], dist);
+        let next = back[other];
+        if next & EDGE != 0 {
+          break to_edge | EDGE;
+        }
+        dist += next;
+      };
+    }
+  }
+
+  // Whatever nothing came to block sees the far edge.
+  for (idx, dist) in ahead.iter_mut().enumerate() {
+    if *dist == UNSEEN {
+      *dist = steps_to_edge(rows, cols, idx / cols, idx % cols, (-d_row, -d_col)) | EDGE;
+    }
+  }
+
+  (back, ahead)
+}
 
+fn analyze<H : Ord + Copy>(grid : &[Vec<H>], set : DirectionSet) -> Views {
   let rows = grid.len();
   let cols = grid[0].len();
+  let heights : Vec<H> = grid.iter().flatten().copied().collect();
+  assert_eq!(heights.len(), rows * cols);
 
-  for row in 0..rows {
-    assert_eq!(cols, grid[row].len());
-    for col in 0..cols {
+  // Every set holds directions in opposite pairs, as DIRECTIONS lists them.
+  let mut distances : [Vec<u32>; 8] = Default::default();
+  for direction in set.directions().iter().step_by(2) {
+    let (_, d_row, d_col) = DIRECTIONS[*direction];
+    (distances[*direction], distances[*direction + 1]) = look(&heights, rows, cols, (d_row, d_col));
+  }
+  Views { rows, cols, set, distances }
+}
 
-      let mut visible_left = 0;
-      for x in (0..col).rev() {
-        visible_left += 1;
-        if grid[row][x] >= grid[row][col] {
-          break;
-        }
-      }
+// Number of trees visible from outside the grid, and the position and score
+// of the tree with the highest scenic score, in one pass over the views.
+fn summarize(views : &Views) -> (usize, (usize, usize, Score)) {
+  let distances : Vec<&[u32]> = views.set.directions().iter().map(|direction| &views.distances[*direction][..]).collect();
+  let mut visible = 0;
+  let mut best = (0, 0);
+  for idx in 0..views.len() {
+    let mut edges = 0;
+    let mut score : Score = 1;
+    for direction in &distances {
+      edges |= direction[idx];
+      score = score.saturating_mul((direction[idx] & !EDGE) as Score);
+    }
+    visible += (edges & EDGE != 0) as usize;
+    if score > best.1 {
+      best = (idx, score);
+    }
+  }
+  (visible, (best.0 / views.cols, best.0 % views.cols, best.1))
+}
 
-      let mut visible_right = 0;
-      for x in col+1..cols {
-        visible_right += 1;
-        if grid[row][x] >= grid[row][col] {
-          break;
+fn trees_visible_from_perimiter(views : &Views) -> usize {
+  summarize(views).0
+}
+
+// Position of the tree with the highest scenic score, and the score.
+fn best_tree(views : &Views) -> (usize, usize, Score) {
+  summarize(views).1
+}
+
+// One line per tree with its height, visibility and viewing distances.
+fn write_csv<H : fmt::Display>(grid : &[Vec<H>], views : &Views, out : &mut impl Write) -> io::Result<()> {
+  let directions = views.set.directions();
+  write!(out, "row,col,height")?;
+  for direction in directions {
+    write!(out, ",visible_{}", DIRECTIONS[*direction].0)?;
+  }
+  for direction in directions {
+    write!(out, ",distance_{}", DIRECTIONS[*direction].0)?;
+  }
+  writeln!(out, ",visible,scenic_score")?;
+
+  for (idx, height) in grid.iter().flatten().enumerate() {
+    write!(out, "{},{},{}", idx / views.cols, idx % views.cols, height)?;
+    for direction in directions {
+      write!(out, ",{}", views.visible_from(idx, *direction) as u8)?;
+    }
+    for direction in directions {
+      write!(out, ",{}", views.distance(idx, *direction))?;
+    }
+    writeln!(out, ",{},{}", views.is_visible(idx) as u8, views.scenic_score(idx))?;
+  }
+  Ok(())
+}
+
+// Draws the tree heights on a background that goes from blue (low scenic
+// score) to red (high), using 24-bit ANSI colours. Trees hidden from every
+// side are dimmed and the best tree is marked with a white background.
+fn write_heatmap<H : fmt::Display>(grid : &[Vec<H>], views : &Views, out : &mut impl Write) -> io::Result<()> {
+  let (best_row, best_col, best_score) = best_tree(views);
+  // Taller heights need a gap to stay readable, and then a fixed width.
+  let width = grid.iter().flatten().map(|height| height.to_string().len()).max().unwrap_or(1);
+  let multi_char = width > 1;
+  let scale = (best_score.max(1) as f64).sqrt();
+
+  for (row, line) in grid.iter().enumerate() {
+    for (col, height) in line.iter().enumerate() {
+      let idx = row * views.cols + col;
+      if (row, col) == (best_row, best_col) {
+        write!(out, "\x1b[1;30;48;2;255;255;255m{:>width$}", height, width = width)?;
+        if multi_char {
+          write!(out, " ")?;
         }
+        continue;
+      }
+      // Square root so the few high scores don't wash everything else out.
+      let heat = (views.scenic_score(idx) as f64).sqrt() / scale;
+      let red = (255.0 * heat) as u8;
+      let blue = (255.0 * (1.0 - heat)) as u8;
+      let style = if views.is_visible(idx) { "1;97" } else { "2;37" };
+      write!(out, "\x1b[{};48;2;{};40;{}m{:>width$}", style, red, blue, height, width = width)?;
+      if multi_char {
+        write!(out, " ")?;
       }
+    }
+    writeln!(out, "\x1b[0m")?;
+  }
+  writeln!(out, "best tree: row {}, col {}, scenic score {}", best_row, best_col, best_score)
+}
+
+// Reference implementation that walks out in every direction from every
+// tree, O(n^3) on an n x n grid. Only used to check analyze, in --bench
+// and the tests.
+fn naive_visible_and_score<H : Ord + Copy>(grid : &[Vec<H>], set : DirectionSet) -> (usize, Score) {
+  let mut visible = 0usize;
+  let mut max_scenic_score : Score = 0;
+
+  let rows = grid.len() as isize;
+  let cols = grid[0].len() as isize;
 
-      let mut visible_top = 0;
-      for y in (0..row).rev() {
-        visible_top += 1;
-        if grid[y][col] >= grid[row][col] {
-          break;
+  for row in 0..rows {
+    for col in 0..cols {
+      let height = grid[row as usize][col as usize];
+      let mut is_visible = false;
+      let mut score : Score = 1;
+
+      for direction in set.directions() {
+        let (_, d_row, d_col) = DIRECTIONS[*direction];
+        let (mut y, mut x) = (row + d_row, col + d_col);
+        let mut dist = 0;
+        let mut blocked = false;
+        while (0..rows).contains(&y) && (0..cols).contains(&x) {
+          dist += 1;
+          if grid[y as usize][x as usize] >= height {
+            blocked = true;
+            break;
+          }
+          y += d_row;
+          x += d_col;
         }
+        is_visible |= !blocked;
+        score = score.saturating_mul(dist);
       }
 
-      let mut visible_bottom = 0;
-      for y in row+1..rows {
-        visible_bottom +=1;
-        if grid[y][col] >= grid[row][col] {
-          break;
-        }
+      if is_visible {
+        visible += 1;
       }
+      max_scenic_score = cmp::max(max_scenic_score, score);
+    }
+  }
 
-      let scenic_score = visible_left * visible_right * visible_top * visible_bottom;
+  (visible, max_scenic_score)
+}
 
-      #[cfg(debug_assertions)]
-      print!("{},", scenic_score);
+// Rows x cols grid of pseudo-random heights below max_height, the same for
+// a given seed. With digits a taller tree is never far away, so even the
+// naive version is quick; a small range gives lots of trees of equal height.
+fn random_grid(rows : usize, cols : usize, max_height : u64, seed : u64) -> Vec<Vec<u64>> {
+  // Xorshift needs a state other than zero.
+  let mut state = seed.wrapping_mul(0x9e3779b97f4a7c15) | 1;
+  let mut next = || {
+    state ^= state << 13;
+    state ^= state >> 7;
+    state ^= state << 17;
+    state % max_height
+  };
+  (0..rows).map(|_| (0..cols).map(|_| next()).collect()).collect()
+}
 
-      max_scenic_score = cmp::max(max_scenic_score, scenic_score);
-    }
+// Square grid that rises left to right and top to bottom, so every tree
+// sees all the way to the left, top and top left edges. This is the grid
+// where the naive version really is O(n^3).
+fn staircase_grid(size : usize) -> Vec<Vec<u32>> {
+  (0..size).map(|row| (0..size).map(|col| (row * size + col) as u32).collect()).collect()
+}
 
-    #[cfg(debug_assertions)]
-    println!("");
+// Times analyze on growing grids, and checks it against the naive version
+// where that still finishes in reasonable time.
+fn bench_grids<H : Ord + Copy>(name : &str, set : DirectionSet, sizes : &[usize], naive_max_size : usize,
+    generate : impl Fn(usize) -> Vec<Vec<H>>) {
+  println!("{} grids", name);
+  println!("{:>6} {:>12} {:>14} {:>10} {:>12}", "size", "visible", "best score", "fast ms", "naive ms");
+  for size in sizes {
+    let grid = generate(*size);
+
+    let start = Instant::now();
+    let views = analyze(&grid, set);
+    let (visible, (_, _, best)) = summarize(&views);
+    let fast_ms = start.elapsed().as_secs_f64() * 1000.0;
+
+    let naive_ms = if *size <= naive_max_size {
+      let start = Instant::now();
+      assert_eq!(naive_visible_and_score(&grid, set), (visible, best));
+      format!("{:.1}", start.elapsed().as_secs_f64() * 1000.0)
+    } else {
+      String::from("-")
+    };
+
+    println!("{:>6} {:>12} {:>14} {:>10.1} {:>12}", size, visible, best, fast_ms, naive_ms);
   }
+}
 
-  max_scenic_score
+fn bench(set : DirectionSet) {
+  let digits = |size| random_grid(size, size, 10, size as u64);
+  bench_grids("random", set, &[100, 250, 500, 1000, 2000, 5000], 1000, digits);
+  println!();
+  bench_grids("staircase", set, &[100, 250, 500, 1000, 2000, 5000], 1000, staircase_grid);
 }
 
-fn main() {
-  let grid = parse_grid(io::stdin().lines());
+enum Export {
+  Csv(String),
+  Heatmap,
+}
+
+fn run<H : Ord + Copy + fmt::Display + From<u8> + FromStr>(format : &InputFormat, set : DirectionSet,
+    export : Option<Export>) -> Result<(), Box<dyn Error>> {
+  let grid : Vec<Vec<H>> = parse_grid(io::stdin().lines(), format)?;
+  let views = analyze(&grid, set);
+
+  match export {
+    Some(Export::Csv(path)) if path == "-" => write_csv(&grid, &views, &mut io::stdout().lock())?,
+    Some(Export::Csv(path)) => write_csv(&grid, &views, &mut io::BufWriter::new(File::create(path)?))?,
+    Some(Export::Heatmap) => write_heatmap(&grid, &views, &mut io::stdout().lock())?,
+    None => (),
+  }
 
-  let result = trees_visible_from_perimiter(&grid);
+  let result = trees_visible_from_perimiter(&views);
   println!("part 1: {}", result);
 
-  let result = scenic_score(&grid);
+  let result = best_tree(&views).2;
   println!("part 2: {}", result);
+
+  Ok(())
+}
+
+fn main() -> Result<(), Box<dyn Error>> {
+  let mut export = None;
+  let mut format = InputFormat::Digits;
+  let mut set = DirectionSet::Cardinal;
+  let mut bench_only = false;
+  let mut args = env::args().skip(1);
+  while let Some(arg) = args.next() {
+    match arg.as_str() {
+      "--bench" => bench_only = true,
+      "--directions" => set = args.next().ok_or("--directions requires cardinal, diagonal or all")?.parse()?,
+      "--csv" => export = Some(Export::Csv(args.next().ok_or("--csv requires a path, or - for stdout")?)),
+      "--heatmap" => export = Some(Export::Heatmap),
+      "--separated" => format = InputFormat::Separated,
+      _ => return Err(format!("unexpected argument: {}", arg).into()),
+    }
+  }
+
+  if bench_only {
+    bench(set);
+    return Ok(());
+  }
+
+  // Single digits fit in a byte, anything else gets the widest type.
+  match format {
+    InputFormat::Digits => run::<u8>(&format, set, export),
+    InputFormat::Separated => run::<u64>(&format, set, export),
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  const SETS : [DirectionSet; 3] = [DirectionSet::Cardinal, DirectionSet::Diagonal, DirectionSet::All];
+
+  fn assert_matches_naive<H : Ord + Copy>(grid : &[Vec<H>]) {
+    for set in SETS {
+      let (visible, (_, _, best)) = summarize(&analyze(grid, set));
+      assert_eq!((visible, best), naive_visible_and_score(grid, set), "{:?}", set);
+    }
+  }
+
+  #[test]
+  fn example() {
+    let lines = include_str!("../assets/example.txt").lines().map(|line| Ok(line.to_string()));
+    let grid : Vec<Vec<u8>> = parse_grid(lines, &InputFormat::Digits).unwrap();
+    let (visible, (row, col, best)) = summarize(&analyze(&grid, DirectionSet::Cardinal));
+    assert_eq!((visible, best), (21, 8));
+    assert_eq!((row, col), (3, 2));
+    assert_matches_naive(&grid);
+  }
+
+  #[test]
+  fn generated_grids() {
+    let shapes = [(1, 1), (1, 7), (7, 1), (2, 2), (5, 9), (9, 5), (31, 31), (40, 17), (50, 50)];
+    for (seed, (rows, cols)) in shapes.into_iter().enumerate() {
+      for max_height in [2, 3, 10, 1000] {
+        assert_matches_naive(&random_grid(rows, cols, max_height, seed as u64));
+      }
+    }
+    assert_matches_naive(&staircase_grid(30));
+  }
+
+  #[test]
+  fn score_larger_than_usize() {
+    // A single taller tree in the middle of a flat grid sees 257 trees in
+    // each of the eight directions, and 257^8 doesn't fit in 64 bits.
+    let mut grid = vec![vec![0u8; 515]; 515];
+    grid[257][257] = 1;
+    let (_, (row, col, best)) = summarize(&analyze(&grid, DirectionSet::All));
+    assert_eq!((row, col, best), (257, 257, (257 as Score).pow(8)));
+    assert_matches_naive(&grid);
+  }
 }
@@ -1,96 +1,129 @@
-use std::collections::HashSet;
+use std::env;
+use std::error::Error;
+use std::fs::File;
 use std::io;
+use std::io::BufWriter;
 
-#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
-struct Point {
-  x : i32,
-  y : i32,
-}
-
-fn update_tail_pos(head_pos : Point, tail_pos : Point) -> Point {
-  let dx = head_pos.x - tail_pos.x;
-  let dy = head_pos.y - tail_pos.y;
+mod render;
+mod rope;
+mod stats;
 
-  assert!(dx.abs() <= 2 && dy.abs() <= 2);
+use render::FrameRate;
+use rope::{Direction, Move, Rope};
 
-  let mut new_tail_pos = tail_pos;
+fn parse_moves(lines : impl Iterator<Item = io::Result<String>>) -> Result<Vec<Move>, Box<dyn Error>> {
+  let mut moves = Vec::new();
 
-  if dx > 1 {
-    new_tail_pos.x = tail_pos.x + dx.abs() - 1;
-  } else if dx < -1 {
-    new_tail_pos.x = tail_pos.x - dx.abs() + 1;
-  } else if dy.abs() > 1 {
-    new_tail_pos.x = head_pos.x;
-  }
-
-  if dy > 1 {
-    new_tail_pos.y = new_tail_pos.y + dy.abs() - 1;
-  } else if dy < -1 {
-    new_tail_pos.y = new_tail_pos.y - dy.abs() + 1;
-  } else if dx.abs() > 1 {
-    new_tail_pos.y = head_pos.y
+  for (idx, line) in lines.enumerate() {
+    let line = line?;
+    let parts : Vec<&str> = line.split_whitespace().collect();
+    let err = |msg : String| format!("line {}: {}", idx + 1, msg);
+
+    let (direction, distance) = match parts.as_slice() {
+      [] => continue,
+      [direction, distance] => (*direction, *distance),
+      _ => return Err(err(format!("expected a direction and a distance, got {:?}", line)).into()),
+    };
+    let direction = direction.parse::<Direction>().map_err(err)?;
+    let distance = distance.parse::<u32>().map_err(|_| err(format!("bad distance: {}", distance)))?;
+    // Any further and the head would fall off the grid in one move.
+    if distance > i32::MAX as u32 {
+      return Err(err(format!("distance {} is more than {}", distance, i32::MAX)).into());
+    }
+    moves.push(Move { direction, distance });
   }
 
-  new_tail_pos
+  Ok(moves)
 }
 
-fn parse_moves(lines : impl Iterator<Item = String>, knot_count : usize) -> HashSet<Point> {
-  assert!(knot_count > 1);
-
-  // Start each knot at origin x=0, y=0.
-  let mut knots = Vec::new();
-  for _ in 0..knot_count {
-    knots.push(Point { x : 0, y : 0 });
+// Runs all the moves on a fresh rope.
+fn simulate(moves : &[Move], knot_count : usize, jumps : bool) -> Result<Rope, String> {
+  let mut rope = Rope::new(knot_count);
+  // --render draws the knots as they go, so there's nothing to look at here.
+  if jumps {
+    rope.jumps(moves).try_for_each(|knots| knots.map(drop))?;
+  } else {
+    rope.steps(moves).try_for_each(|knots| knots.map(drop))?;
   }
+  Ok(rope)
+}
 
-  let mut tail_positions = HashSet::new();
+const KNOT_COUNT : usize = 10;
 
-  for line in lines {
-    let parts : Vec<&str> = line.split_whitespace().collect();
-    let direction = parts[0];
-    let distance = parts[1].parse::<u32>().unwrap();
-
-    // Process each move in increments of one.
-    for _ in 0..distance {
-      knots[0] = match direction {
-        "U" => Point { x : knots[0].x, y : knots[0].y + 1 },
-        "D" => Point { x : knots[0].x, y : knots[0].y - 1 },
-        "L" => Point { x : knots[0].x - 1, y : knots[0].y },
-        "R" => Point { x : knots[0].x + 1, y : knots[0].y },
-        _ => panic!("unexpected direction token {}", direction),
-      };
-
-      // Update each trailing knot after every move.
-      for i in 1..knot_count {
-        knots[i] = update_tail_pos(knots[i-1], knots[i]);
-      }
-
-      // Keep track of positions visited by the last knot only.
-      tail_positions.insert(knots[knot_count - 1]);
-    }
+enum Heatmap {
+  Csv(String),
+  Ppm(String),
+}
 
-    #[cfg(debug_assertions)]
-    println!("{:?}", knots);
+fn main() -> Result<(), Box<dyn Error>> {
+  // Whether the head covers each move in one jump instead of unit steps.
+  let mut jumps = false;
+  let mut rate = None;
+  let mut frames_path = None;
+  let mut knot_count = KNOT_COUNT;
+  let mut stats = false;
+  let mut heatmap = None;
+  // Defaults to the tail.
+  let mut heatmap_knot = None;
+
+  let mut args = env::args().skip(1);
+  while let Some(arg) = args.next() {
+    let mut value = || args.next().ok_or(format!("{} requires a value", arg));
+    match arg.as_str() {
+      "--jumps" => jumps = true,
+      "--render" => {
+        rate = match value()?.as_str() {
+          "moves" => Some(FrameRate::PerMove),
+          "steps" => Some(FrameRate::PerStep),
+          other => return Err(format!("--render expects moves or steps, got {}", other).into()),
+        };
+      },
+      "--frames" => frames_path = Some(value()?),
+      "--knots" => knot_count = value()?.parse::<usize>()?,
+      "--stats" => stats = true,
+      "--csv" => heatmap = Some(Heatmap::Csv(value()?)),
+      "--ppm" => heatmap = Some(Heatmap::Ppm(value()?)),
+      "--heatmap-knot" => heatmap_knot = Some(value()?.parse::<usize>()?),
+      _ => return Err(format!("unexpected argument: {}", arg).into()),
+    }
+  }
+  if knot_count < 1 {
+    return Err("--knots needs at least one knot".into());
+  }
+  let heatmap_knot = heatmap_knot.unwrap_or(knot_count - 1);
+  if heatmap_knot >= knot_count {
+    return Err(format!("--heatmap-knot must be below the knot count {}", knot_count).into());
   }
 
-  tail_positions
-}
+  let moves = parse_moves(io::stdin().lines())?;
 
-fn main() {
-  let mut stdin : Vec<String> = Vec::new();
-  for line in io::stdin().lines() {
-    stdin.push(line.unwrap().to_string());
+  if let Some(rate) = rate {
+    match frames_path {
+      Some(path) => render::render(&moves, knot_count, jumps, rate, &mut BufWriter::new(File::create(path)?))?,
+      None => render::render(&moves, knot_count, jumps, rate, &mut io::stdout().lock())?,
+    }
+    return Ok(());
   }
 
-  let tail_positions = parse_moves(stdin.clone().into_iter(), 2);
-
-  #[cfg(debug_assertions)]
-  println!("{:?}", tail_positions);
-  println!("part 1: {}", tail_positions.len());
+  if stats || heatmap.is_some() {
+    let rope = simulate(&moves, knot_count, jumps)?;
+    if stats {
+      stats::print_stats(&rope);
+    }
+    match heatmap {
+      Some(Heatmap::Csv(path)) if path == "-" => stats::write_visits_csv(&rope, heatmap_knot, &mut io::stdout().lock())?,
+      Some(Heatmap::Csv(path)) => stats::write_visits_csv(&rope, heatmap_knot, &mut BufWriter::new(File::create(path)?))?,
+      Some(Heatmap::Ppm(path)) => stats::write_visits_ppm(&rope, heatmap_knot, &mut BufWriter::new(File::create(path)?))?,
+      None => (),
+    }
+    return Ok(());
+  }
 
-  let tail_positions = parse_moves(stdin.clone().into_iter(), 10);
+  // The knot right behind the head moves exactly like the tail of a two
+  // knot rope, so one ten knot rope answers both parts.
+  let rope = simulate(&moves, KNOT_COUNT, jumps)?;
+  println!("part 1: {}", rope.visits(1).len());
+  println!("part 2: {}", rope.visits(KNOT_COUNT - 1).len());
 
-  #[cfg(debug_assertions)]
-  println!("{:?}", tail_positions);
-  println!("part 2: {}", tail_positions.len());
+  Ok(())
 }
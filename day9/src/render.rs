@@ -0,0 +1,95 @@
+use std::error::Error;
+use std::io;
+use std::io::Write;
+
+use crate::rope::{Bounds, Move, Point, Rope};
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum FrameRate {
+  PerMove,
+  PerStep,
+}
+
+// Label for a knot, like the puzzle's illustrations: H for the head, T for
+// the tail of a two knot rope, and numbers for everything else.
+fn knot_label(knot : usize, knot_count : usize) -> char {
+  match knot {
+    0 => 'H',
+    1 if knot_count == 2 => 'T',
+    // Letters once the digits run out.
+    _ => char::from_digit(knot as u32, 36).unwrap_or('*'),
+  }
+}
+
+// Draws one frame: knots first, lower numbers on top, then the start and
+// the cells the tail has been in.
+fn write_frame(rope : &Rope, bounds : &Bounds, out : &mut impl Write) -> io::Result<()> {
+  let knots = rope.knots();
+  let trail = rope.visits(knots.len() - 1);
+
+  let mut row = vec!['.'; bounds.width()];
+  for y in (bounds.min.y..=bounds.max.y).rev() {
+    for (col, x) in (bounds.min.x..=bounds.max.x).enumerate() {
+      let point = Point { x, y };
+      row[col] = match knots.iter().position(|knot| *knot == point) {
+        Some(knot) => knot_label(knot, knots.len()),
+        None if point == Rope::START => 's',
+        None if trail.contains_key(&point) => '#',
+        None => '.',
+      };
+    }
+    writeln!(out, "{}", row.iter().collect::<String>())?;
+  }
+  writeln!(out)
+}
+
+// Unit steps a move takes, or a single step if the head jumps.
+fn step_count(mv : &Move, jumps : bool) -> u32 {
+  if jumps { 1 } else { mv.distance }
+}
+
+fn take_step(rope : &mut Rope, mv : &Move, jumps : bool) -> Result<(), String> {
+  if jumps {
+    rope.jump(mv)
+  } else {
+    rope.step(mv.direction)
+  }
+}
+
+// Replays the moves on a fresh rope and draws a frame after every move, or
+// every unit step, on a grid big enough for every frame.
+pub fn render(moves : &[Move], knot_count : usize, jumps : bool, rate : FrameRate,
+    out : &mut impl Write) -> Result<(), Box<dyn Error>> {
+  // Run the whole thing once to find out how big the grid has to be.
+  let mut rope = Rope::new(knot_count);
+  for mv in moves {
+    for _ in 0..step_count(mv, jumps) {
+      take_step(&mut rope, mv, jumps)?;
+    }
+  }
+  let mut bounds = Bounds::new(Rope::START);
+  for knot in 0..knot_count {
+    for point in rope.visits(knot).keys() {
+      bounds.include(*point);
+    }
+  }
+
+  let mut rope = Rope::new(knot_count);
+  writeln!(out, "== Initial State ==\n")?;
+  write_frame(&rope, &bounds, out)?;
+
+  for mv in moves {
+    let steps = step_count(mv, jumps);
+    for step in 1..=steps {
+      take_step(&mut rope, mv, jumps)?;
+      // The last step gets the frame for the whole move.
+      if rate == FrameRate::PerStep && step < steps {
+        writeln!(out, "== {} (step {} of {}) ==\n", mv, step, steps)?;
+        write_frame(&rope, &bounds, out)?;
+      }
+    }
+    writeln!(out, "== {} ==\n", mv)?;
+    write_frame(&rope, &bounds, out)?;
+  }
+  Ok(())
+}
@@ -0,0 +1,319 @@
+use std::cmp;
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct Point {
+  pub x : i32,
+  pub y : i32,
+}
+
+// Smallest rectangle holding a set of points, inclusive on every side.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Bounds {
+  pub min : Point,
+  pub max : Point,
+}
+
+impl Bounds {
+  pub fn new(point : Point) -> Bounds {
+    Bounds { min : point, max : point }
+  }
+
+  pub fn include(&mut self, point : Point) {
+    self.min = Point { x : cmp::min(self.min.x, point.x), y : cmp::min(self.min.y, point.y) };
+    self.max = Point { x : cmp::max(self.max.x, point.x), y : cmp::max(self.max.y, point.y) };
+  }
+
+  pub fn width(&self) -> usize {
+    (self.max.x as i64 - self.min.x as i64 + 1) as usize
+  }
+
+  pub fn height(&self) -> usize {
+    (self.max.y as i64 - self.min.y as i64 + 1) as usize
+  }
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Direction {
+  Up,
+  Down,
+  Left,
+  Right,
+  UpLeft,
+  UpRight,
+  DownLeft,
+  DownRight,
+}
+
+impl Direction {
+  // How far one step in this direction goes along x and y.
+  pub fn offset(&self) -> (i32, i32) {
+    match self {
+      Direction::Up => (0, 1),
+      Direction::Down => (0, -1),
+      Direction::Left => (-1, 0),
+      Direction::Right => (1, 0),
+      Direction::UpLeft => (-1, 1),
+      Direction::UpRight => (1, 1),
+      Direction::DownLeft => (-1, -1),
+      Direction::DownRight => (1, -1),
+    }
+  }
+}
+
+impl Direction {
+  const TOKENS : [(&'static str, Direction); 8] = [
+    ("U", Direction::Up),
+    ("D", Direction::Down),
+    ("L", Direction::Left),
+    ("R", Direction::Right),
+    ("UL", Direction::UpLeft),
+    ("UR", Direction::UpRight),
+    ("DL", Direction::DownLeft),
+    ("DR", Direction::DownRight),
+  ];
+}
+
+impl FromStr for Direction {
+  type Err = String;
+
+  fn from_str(s : &str) -> Result<Self, Self::Err> {
+    Direction::TOKENS.iter()
+      .find(|(token, _)| *token == s)
+      .map(|(_, direction)| *direction)
+      .ok_or(format!("unexpected direction token {}", s))
+  }
+}
+
+impl fmt::Display for Direction {
+  fn fmt(&self, f : &mut fmt::Formatter) -> fmt::Result {
+    let (token, _) = Direction::TOKENS.iter().find(|(_, direction)| direction == self).unwrap();
+    write!(f, "{}", token)
+  }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct Move {
+  pub direction : Direction,
+  pub distance : u32,
+}
+
+impl fmt::Display for Move {
+  fn fmt(&self, f : &mut fmt::Formatter) -> fmt::Result {
+    write!(f, "{} {}", self.direction, self.distance)
+  }
+}
+
+// Moves knot towards leader until they touch, one step at a time along each
+// axis that still differs, calling visit for every cell on the way. Returns
+// the new position of knot.
+fn follow(leader : Point, knot : Point, mut visit : impl FnMut(Point)) -> Point {
+  let dx = leader.x - knot.x;
+  let dy = leader.y - knot.y;
+  let steps = cmp::max(dx.abs(), dy.abs()) - 1;
+
+  let mut pos = knot;
+  for i in 1..=steps {
+    pos = Point {
+      x : knot.x + dx.signum() * cmp::min(dx.abs(), i),
+      y : knot.y + dy.signum() * cmp::min(dy.abs(), i),
+    };
+    visit(pos);
+  }
+  pos
+}
+
+// A rope of knots that all start at the origin. The first knot is the head
+// and every other knot follows the one before it.
+pub struct Rope {
+  knots : Vec<Point>,
+  // How many times each knot has moved into each cell. The start is there
+  // from the beginning, with a count of zero until a knot comes back.
+  visits : Vec<HashMap<Point, u32>>,
+  // Cells each knot has moved, counting a diagonal step as one.
+  travelled : Vec<u64>,
+}
+
+impl Rope {
+  pub const START : Point = Point { x : 0, y : 0 };
+
+  pub fn new(knot_count : usize) -> Rope {
+    assert!(knot_count > 0);
+    Rope {
+      knots : vec![Rope::START; knot_count],
+      visits : vec![HashMap::from([(Rope::START, 0)]); knot_count],
+      travelled : vec![0; knot_count],
+    }
+  }
+
+  pub fn knots(&self) -> &[Point] {
+    &self.knots
+  }
+
+  pub fn visits(&self, knot : usize) -> &HashMap<Point, u32> {
+    &self.visits[knot]
+  }
+
+  pub fn travelled(&self, knot : usize) -> u64 {
+    self.travelled[knot]
+  }
+
+  // Moves the head one step and lets the rest of the rope catch up.
+  pub fn step(&mut self, direction : Direction) -> Result<(), String> {
+    self.move_head(direction, 1)
+  }
+
+  // Moves the head the whole distance at once, then lets the rest of the
+  // rope catch up. Only the end of the move can be seen, but every knot goes
+  // through the same cells as it would one step at a time.
+  pub fn jump(&mut self, mv : &Move) -> Result<(), String> {
+    self.move_head(mv.direction, mv.distance)
+  }
+
+  // Fails, without moving anything, if the head would leave the i32 grid.
+  fn move_head(&mut self, direction : Direction, distance : u32) -> Result<(), String> {
+    let (dx, dy) = direction.offset();
+    let head = self.knots[0];
+    // The rest of the rope stays between where the head has been.
+    let reach = |start : i32, step : i32| start.checked_add(step.checked_mul(i32::try_from(distance).ok()?)?);
+    if reach(head.x, dx).is_none() || reach(head.y, dy).is_none() {
+      return Err(format!("{} {} from ({}, {}) goes off the grid", direction, distance, head.x, head.y));
+    }
+
+    // The head goes a chunk at a time so long moves don't need one huge path.
+    const CHUNK : i32 = 4096;
+    let distance = distance as i32;
+    for start in (0..distance).step_by(CHUNK as usize) {
+      let mut path : Vec<Point> = (start + 1..=start + cmp::min(CHUNK, distance - start))
+        .map(|i| Point { x : head.x + dx * i, y : head.y + dy * i })
+        .collect();
+      self.follow_path(&mut path);
+    }
+    Ok(())
+  }
+
+  // Moves the head through path, and each knot after it through every cell
+  // the one before it went through, in order, which is all that unit steps
+  // would show it.
+  fn follow_path(&mut self, path : &mut Vec<Point>) {
+    for i in 0..self.knots.len() {
+      if i > 0 {
+        let mut followed = Vec::with_capacity(path.len());
+        let mut knot = self.knots[i];
+        for leader in path.iter() {
+          knot = follow(*leader, knot, |pos| followed.push(pos));
+        }
+        *path = followed;
+      }
+      let Some(last) = path.last() else {
+        // Nothing further back can move either.
+        break;
+      };
+      self.knots[i] = *last;
+      for pos in path.iter() {
+        *self.visits[i].entry(*pos).or_insert(0) += 1;
+      }
+      self.travelled[i] += path.len() as u64;
+    }
+  }
+
+  // Applies the moves one unit step at a time, yielding the knot positions
+  // after each step.
+  pub fn steps<'a>(&'a mut self, moves : &'a [Move]) -> impl Iterator<Item = Result<Vec<Point>, String>> + 'a {
+    moves.iter()
+      .flat_map(|mv| (0..mv.distance).map(|_| mv.direction))
+      .map(|direction| {
+        self.step(direction)?;
+        Ok(self.knots.clone())
+      })
+  }
+
+  // Like steps, but each move is a single jump.
+  pub fn jumps<'a>(&'a mut self, moves : &'a [Move]) -> impl Iterator<Item = Result<Vec<Point>, String>> + 'a {
+    moves.iter().map(|mv| {
+      self.jump(mv)?;
+      Ok(self.knots.clone())
+    })
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  // Pseudo-random moves in all eight directions, including zero distances.
+  fn generate_moves(count : usize, max_distance : u32, seed : u64) -> Vec<Move> {
+    // Knuth's MMIX LCG; the high bits are random enough for this.
+    let mut state = seed;
+    let mut next = || {
+      state = state.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+      state >> 33
+    };
+    (0..count).map(|_| Move {
+      direction : Direction::TOKENS[(next() % 8) as usize].1,
+      distance : (next() % (max_distance as u64 + 1)) as u32,
+    }).collect()
+  }
+
+  fn assert_jumps_match_steps(moves : &[Move], knot_count : usize) {
+    let mut stepped = Rope::new(knot_count);
+    stepped.steps(moves).try_for_each(|knots| knots.map(drop)).unwrap();
+    let mut jumped = Rope::new(knot_count);
+    jumped.jumps(moves).try_for_each(|knots| knots.map(drop)).unwrap();
+
+    assert_eq!(jumped.knots(), stepped.knots());
+    for knot in 0..knot_count {
+      assert_eq!(jumped.visits(knot), stepped.visits(knot), "knot {}", knot);
+      assert_eq!(jumped.travelled(knot), stepped.travelled(knot), "knot {}", knot);
+    }
+  }
+
+  #[test]
+  fn jumps_match_steps() {
+    for seed in 0..50 {
+      for knot_count in [1, 2, 3, 10, 25] {
+        assert_jumps_match_steps(&generate_moves(200, 1 + seed as u32 % 20, seed), knot_count);
+      }
+    }
+    // Long enough to take several chunks.
+    for seed in 0..2 {
+      assert_jumps_match_steps(&generate_moves(6, 12000, seed), 10);
+    }
+  }
+
+  #[test]
+  fn input_jumps_match_steps() {
+    let lines = include_str!("../assets/input.txt").lines().map(|line| Ok(line.to_string()));
+    let moves = crate::parse_moves(lines).unwrap();
+    assert_jumps_match_steps(&moves, 10);
+
+    let mut rope = Rope::new(10);
+    rope.jumps(&moves).try_for_each(|knots| knots.map(drop)).unwrap();
+    assert_eq!((rope.visits(1).len(), rope.visits(9).len()), (6470, 2658));
+  }
+
+  #[test]
+  fn moves_off_the_grid() {
+    let mut rope = Rope::new(3);
+    let far = Move { direction : Direction::Right, distance : i32::MAX as u32 };
+    rope.jump(&Move { direction : Direction::Right, distance : 1 }).unwrap();
+    assert!(rope.jump(&far).is_err());
+    assert!(rope.jump(&Move { direction : Direction::Up, distance : u32::MAX }).is_err());
+    // Nothing moved.
+    assert_eq!(rope.knots(), [Point { x : 1, y : 0 }, Rope::START, Rope::START]);
+    assert_eq!(rope.travelled(0), 1);
+
+    // Right up against the edge.
+    let edge = i32::MAX - 3;
+    rope.knots = vec![Point { x : edge, y : i32::MIN }; 3];
+    rope.jump(&Move { direction : Direction::DownRight, distance : 0 }).unwrap();
+    rope.jump(&Move { direction : Direction::Right, distance : 3 }).unwrap();
+    assert_eq!(rope.knots(), [Point { x : i32::MAX, y : i32::MIN }, Point { x : i32::MAX - 1, y : i32::MIN },
+      Point { x : i32::MAX - 2, y : i32::MIN }]);
+    assert!(rope.step(Direction::Right).is_err());
+    assert!(rope.step(Direction::Down).is_err());
+    assert!(rope.step(Direction::Left).is_ok());
+  }
+}
@@ -0,0 +1,87 @@
+use std::io;
+use std::io::Write;
+
+use crate::rope::{Bounds, Point, Rope};
+
+pub struct KnotStats {
+  // Distinct cells the knot has been in, including the start.
+  pub distinct : usize,
+  pub travelled : u64,
+  pub bounds : Bounds,
+}
+
+pub fn knot_stats(rope : &Rope) -> Vec<KnotStats> {
+  (0..rope.knots().len()).map(|knot| {
+    let visits = rope.visits(knot);
+    let mut bounds = Bounds::new(Rope::START);
+    for point in visits.keys() {
+      bounds.include(*point);
+    }
+    KnotStats { distinct : visits.len(), travelled : rope.travelled(knot), bounds }
+  }).collect()
+}
+
+pub fn print_stats(rope : &Rope) {
+  println!("{:>5} {:>10} {:>10} {:>23}", "knot", "distinct", "travelled", "bounding box");
+  for (knot, stats) in knot_stats(rope).iter().enumerate() {
+    let bounds = format!("({},{})..({},{})", stats.bounds.min.x, stats.bounds.min.y, stats.bounds.max.x, stats.bounds.max.y);
+    println!("{:>5} {:>10} {:>10} {:>23}", knot, stats.distinct, stats.travelled, bounds);
+  }
+}
+
+// Every cell the knot has been in and how many times it moved into it,
+// ordered top to bottom and left to right like the rendered frames.
+pub fn write_visits_csv(rope : &Rope, knot : usize, out : &mut impl Write) -> io::Result<()> {
+  let mut visits : Vec<(Point, u32)> = rope.visits(knot).iter().map(|(point, count)| (*point, *count)).collect();
+  visits.sort_by_key(|(point, _)| (-point.y, point.x));
+
+  writeln!(out, "x,y,visits")?;
+  for (point, count) in visits {
+    writeln!(out, "{},{},{}", point.x, point.y, count)?;
+  }
+  Ok(())
+}
+
+// Pixels per cell side in the heatmap image.
+const CELL_SIZE : usize = 4;
+
+// Binary PPM image of how often the knot moved into each cell, from dark
+// blue (once) to yellow (most often). Cells it never entered are black and
+// the start is white.
+pub fn write_visits_ppm(rope : &Rope, knot : usize, out : &mut impl Write) -> io::Result<()> {
+  let visits = rope.visits(knot);
+  let mut bounds = Bounds::new(Rope::START);
+  for point in visits.keys() {
+    bounds.include(*point);
+  }
+  let most = visits.values().copied().max().unwrap_or(0).max(1);
+  // Visit counts span orders of magnitude between the cells the rope keeps
+  // crossing and the ones it passes once, so colour by their logarithm.
+  let scale = (most as f64).ln_1p();
+
+  let (width, height) = (bounds.width() * CELL_SIZE, bounds.height() * CELL_SIZE);
+  writeln!(out, "P6\n{} {}\n255", width, height)?;
+
+  let mut row = Vec::with_capacity(width * 3);
+  for y in (bounds.min.y..=bounds.max.y).rev() {
+    row.clear();
+    for x in bounds.min.x..=bounds.max.x {
+      let point = Point { x, y };
+      let pixel = match visits.get(&point) {
+        _ if point == Rope::START => [255, 255, 255],
+        Some(count) if *count > 0 => {
+          let heat = (*count as f64).ln_1p() / scale;
+          [(255.0 * heat) as u8, (220.0 * heat) as u8, (160.0 * (1.0 - heat) + 40.0) as u8]
+        },
+        _ => [0, 0, 0],
+      };
+      for _ in 0..CELL_SIZE {
+        row.extend_from_slice(&pixel);
+      }
+    }
+    for _ in 0..CELL_SIZE {
+      out.write_all(&row)?;
+    }
+  }
+  Ok(())
+}